@@ -0,0 +1,99 @@
+//! Fan-out cost of `WsServer::broadcast_event` with 1, 100 and 1000 connected
+//! WebSocket clients.
+//!
+//! Clients are real loopback connections that read and discard frames, so the
+//! measured time covers serialization plus queueing one frame per client.
+//!
+//! ```bash
+//! cargo bench --bench broadcast
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures_util::StreamExt;
+use parser_proxy_ws::{Config, WsServer};
+use sol_parser_sdk::core::events::{EventMetadata, PumpFunTradeEvent};
+use sol_parser_sdk::DexEvent;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::runtime::Runtime;
+
+fn sample_event() -> DexEvent {
+    DexEvent::PumpFunTrade(PumpFunTradeEvent {
+        metadata: EventMetadata {
+            signature: Signature::from([7u8; 64]),
+            slot: 312_345_678,
+            tx_index: 42,
+            block_time_us: 1_730_000_000_000_000,
+            grpc_recv_us: 1_730_000_000_000_123,
+        },
+        mint: Pubkey::new_unique(),
+        sol_amount: 1_500_000_000,
+        token_amount: 35_000_000_000_000,
+        is_buy: true,
+        is_created_buy: false,
+        user: Pubkey::new_unique(),
+        timestamp: 1_730_000_000,
+        virtual_sol_reserves: 30_000_000_000,
+        virtual_token_reserves: 1_073_000_000_000_000,
+        real_sol_reserves: 1_000_000_000,
+        real_token_reserves: 793_100_000_000_000,
+        fee_recipient: Pubkey::new_unique(),
+        fee_basis_points: 95,
+        fee: 14_250_000,
+        creator: Pubkey::new_unique(),
+        creator_fee_basis_points: 5,
+        creator_fee: 750_000,
+        track_volume: true,
+        total_unclaimed_tokens: 0,
+        total_claimed_tokens: 0,
+        current_sol_volume: 0,
+        last_update_timestamp: 1_730_000_000,
+    })
+}
+
+/// Start a server on an ephemeral port and connect `clients` readers to it
+fn start_server(runtime: &Runtime, clients: usize) -> Arc<WsServer> {
+    runtime.block_on(async {
+        let server = Arc::new(WsServer::new(&Config::default().server));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let accept_server = server.clone();
+        tokio::spawn(async move { accept_server.run(listener).await });
+
+        for _ in 0..clients {
+            let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+                .await
+                .unwrap();
+            tokio::spawn(ws.for_each(|_| async {}));
+        }
+
+        // Give the server time to register every client
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        server
+    })
+}
+
+fn bench_broadcast(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let event = sample_event();
+
+    let mut group = c.benchmark_group("broadcast_event");
+    for clients in [1usize, 100, 1000] {
+        let server = start_server(&runtime, clients);
+        group.throughput(Throughput::Elements(clients as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(clients), &clients, |b, _| {
+            b.iter(|| server.broadcast_event(&event));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_broadcast);
+criterion_main!(benches);
@@ -5,40 +5,11 @@ import websockets
 import json
 import time
 from datetime import datetime
-from base58 import b58encode
 
-def array_to_base58(arr):
-    """将字节数组转换为 base58 字符串"""
-    try:
-        return b58encode(bytes(arr)).decode('utf-8')
-    except:
-        return ','.join(str(x) for x in arr)
-
-def convert_arrays_in_object(obj, path=''):
-    """递归转换对象中的所有 Pubkey 和 Signature 数组"""
-    if isinstance(obj, list):
-        # 检查是否是 Signature (64 字节数组)
-        if len(obj) == 64 and all(isinstance(x, int) and 0 <= x <= 255 for x in obj):
-            if path.endswith('.signature') or path == 'signature':
-                return array_to_base58(obj)
-
-        # 检查是否是 Pubkey (32 字节数组)
-        if len(obj) == 32 and all(isinstance(x, int) and 0 <= x <= 255 for x in obj):
-            return array_to_base58(obj)
-
-        return [convert_arrays_in_object(item, f"{path}[{idx}]") for idx, item in enumerate(obj)]
-
-    elif isinstance(obj, dict):
-        converted = {}
-        for key, value in obj.items():
-            new_path = f"{path}.{key}" if path else key
-            converted[key] = convert_arrays_in_object(value, new_path)
-        return converted
-
-    return obj
 
 async def listen_events():
-    uri = "ws://127.0.0.1:9001"
+    # 让服务器直接输出 base58 格式的 Pubkey / Signature
+    uri = "ws://127.0.0.1:9001/?keys=base58"
 
     print("🚀 Connecting to WebSocket server...")
 
@@ -52,10 +23,7 @@ async def listen_events():
                     # 客户端接收时间（微秒）
                     client_recv_us = int(time.time() * 1_000_000)
 
-                    raw_event = json.loads(message)
-
-                    # 转换所有 Pubkey 和 Signature 数组为字符串
-                    event = convert_arrays_in_object(raw_event)
+                    event = json.loads(message)
 
                     # 计算延迟
                     event_data = list(event.values())[0] if event else {}
@@ -1,55 +1,8 @@
 import WebSocket from 'ws';
-import { PublicKey } from '@solana/web3.js';
-import bs58 from 'bs58';
 import { performance } from 'perf_hooks';
 
-// Pubkey 数组转换为 base58 字符串
-function pubkeyArrayToString(arr: number[]): string {
-    try {
-        const buffer = Buffer.from(arr);
-        return new PublicKey(buffer).toBase58();
-    } catch (e) {
-        return arr.join(',');
-    }
-}
-
-// Signature 数组转换为 base58 字符串
-function signatureArrayToString(arr: number[]): string {
-    try {
-        const buffer = Buffer.from(arr);
-        return bs58.encode(buffer);
-    } catch (e) {
-        return arr.join(',');
-    }
-}
-
-// 递归转换对象中的所有 Pubkey 和 Signature 数组
-function convertArraysInObject(obj: any, path: string = ''): any {
-    if (Array.isArray(obj)) {
-        // 检查是否是 Signature (64 字节数组)
-        if (obj.length === 64 && obj.every(n => typeof n === 'number' && n >= 0 && n <= 255)) {
-            // 如果字段名是 signature，转换为 base58
-            if (path.endsWith('.signature') || path === 'signature') {
-                return signatureArrayToString(obj);
-            }
-        }
-        // 检查是否是 Pubkey (32 字节数组)
-        if (obj.length === 32 && obj.every(n => typeof n === 'number' && n >= 0 && n <= 255)) {
-            return pubkeyArrayToString(obj);
-        }
-        return obj.map((item, idx) => convertArraysInObject(item, `${path}[${idx}]`));
-    } else if (obj && typeof obj === 'object') {
-        const converted: any = {};
-        for (const [key, value] of Object.entries(obj)) {
-            const newPath = path ? `${path}.${key}` : key;
-            converted[key] = convertArraysInObject(value, newPath);
-        }
-        return converted;
-    }
-    return obj;
-}
-
-const ws = new WebSocket('ws://127.0.0.1:9001');
+// 让服务器直接输出 base58 格式的 Pubkey / Signature
+const ws = new WebSocket('ws://127.0.0.1:9001/?keys=base58');
 
 ws.on('open', function open() {
     console.log('✅ Connected to WebSocket server');
@@ -64,10 +17,7 @@ ws.on('message', function message(data: WebSocket.Data) {
     const clientRecvUs = Math.floor(nowMs * 1000);
 
     try {
-        const rawEvent = JSON.parse(data.toString());
-
-        // 转换所有 Pubkey 和 Signature 数组为字符串
-        const event = convertArraysInObject(rawEvent);
+        const event = JSON.parse(data.toString());
 
         // 计算延迟
         // 从 metadata 中提取 grpc_recv_us
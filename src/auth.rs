@@ -0,0 +1,270 @@
+use crate::config::{ApiKeyConfig, AuthConfig};
+use crate::event_types::{parse_event_type, protocol_name, EventTypeSet, EVENT_TYPES};
+use crate::upstream::now_us;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::handshake::server::Request;
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+
+/// Query parameter carrying the API key, for clients that can't set headers
+const KEY_PARAM: &str = "api_key";
+const KEY_HEADER: &str = "x-api-key";
+
+/// The API keys a server accepts
+pub struct Auth {
+    keys: HashMap<String, Arc<ApiKey>>,
+}
+
+/// A key's entitlements and its live usage
+pub struct ApiKey {
+    pub name: String,
+    /// Event types the key may receive
+    pub allowed: EventTypeSet,
+    max_connections: Option<usize>,
+    connections: AtomicUsize,
+    rate: Option<RateLimiter>,
+}
+
+/// Why an upgrade request was refused
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// No key, or one that isn't configured
+    Unauthorized,
+    /// The key already has `max_connections` open
+    TooManyConnections { name: String, limit: usize },
+}
+
+#[derive(Deserialize)]
+struct KeysFile {
+    #[serde(default)]
+    keys: Vec<ApiKeyConfig>,
+}
+
+impl Auth {
+    pub fn load(config: &AuthConfig) -> Result<Self> {
+        let mut entries = config.keys.clone();
+        if let Some(path) = &config.keys_file {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read keys file: {}", path))?;
+            let file: KeysFile = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse keys file: {}", path))?;
+            entries.extend(file.keys);
+        }
+
+        let mut keys = HashMap::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let key = ApiKey::new(entry, i)?;
+            if keys.insert(entry.key.clone(), Arc::new(key)).is_some() {
+                bail!("API key {} is configured twice", entry.name.as_deref().unwrap_or("(unnamed)"));
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    /// Check the key of an upgrade request and reserve one of its connections;
+    /// the connection is released when the returned `Grant` is dropped
+    pub fn admit(&self, request: &Request) -> Result<Grant, Rejection> {
+        let key = request_key(request)
+            .and_then(|key| self.keys.get(key))
+            .ok_or(Rejection::Unauthorized)?;
+
+        let reserved = key.connections.fetch_update(Ordering::AcqRel, Ordering::Acquire, |open| {
+            key.max_connections.is_none_or(|limit| open < limit).then_some(open + 1)
+        });
+        match reserved {
+            Ok(_) => Ok(Grant(key.clone())),
+            Err(_) => Err(Rejection::TooManyConnections {
+                name: key.name.clone(),
+                limit: key.max_connections.unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// Whether a plain HTTP request carries a configured key; unlike `admit`,
+    /// it reserves no connection
+    pub fn recognizes(&self, request: &Request) -> bool {
+        request_key(request).is_some_and(|key| self.keys.contains_key(key))
+    }
+}
+
+/// `Authorization: Bearer <key>`, `X-API-Key: <key>` or `?api_key=<key>`
+fn request_key(request: &Request) -> Option<&str> {
+    let headers = request.headers();
+    if let Some(value) = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok()) {
+        if let Some(key) = value.strip_prefix("Bearer ") {
+            return Some(key.trim());
+        }
+    }
+    if let Some(value) = headers.get(KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(value.trim());
+    }
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(KEY_PARAM)?.strip_prefix('='))
+}
+
+impl ApiKey {
+    fn new(config: &ApiKeyConfig, index: usize) -> Result<Self> {
+        let name = config.name.clone().unwrap_or_else(|| format!("#{}", index + 1));
+
+        let mut allowed = EventTypeSet::all();
+        if !config.event_types.is_empty() {
+            allowed = EventTypeSet::empty();
+            for event_type in &config.event_types {
+                match parse_event_type(event_type) {
+                    Some(event_type) => allowed.insert(event_type),
+                    None => bail!("API key {}: unknown event type {}", name, event_type),
+                }
+            }
+        }
+        if !config.protocols.is_empty() {
+            if let Some(unknown) = config
+                .protocols
+                .iter()
+                .find(|p| !EVENT_TYPES.iter().any(|(_, t)| protocol_name(*t) == p.as_str()))
+            {
+                bail!("API key {}: unknown protocol {}", name, unknown);
+            }
+            allowed = allowed
+                .iter()
+                .filter(|t| config.protocols.iter().any(|p| p == protocol_name(*t)))
+                .collect();
+        }
+
+        Ok(Self {
+            name,
+            allowed,
+            max_connections: config.max_connections,
+            connections: AtomicUsize::new(0),
+            rate: config.max_messages_per_sec.map(RateLimiter::new),
+        })
+    }
+
+    /// Take one event from the key's per-second allowance
+    #[inline]
+    pub fn allow_message(&self) -> bool {
+        self.rate.as_ref().is_none_or(|rate| rate.try_acquire())
+    }
+}
+
+/// Holds one of a key's connection slots
+pub struct Grant(Arc<ApiKey>);
+
+impl Grant {
+    pub fn key(&self) -> &Arc<ApiKey> {
+        &self.0
+    }
+}
+
+impl Drop for Grant {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Counts events per wall-clock second; the count restarts with each second
+struct RateLimiter {
+    limit: u64,
+    second: AtomicU64,
+    count: AtomicU64,
+}
+
+impl RateLimiter {
+    fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            second: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    fn try_acquire(&self) -> bool {
+        let now = now_us() as u64 / 1_000_000;
+        let second = self.second.load(Ordering::Relaxed);
+        if second != now
+            && self
+                .second
+                .compare_exchange(second, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.count.store(0, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed) < self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> ApiKeyConfig {
+        ApiKeyConfig {
+            key: key.to_string(),
+            name: None,
+            protocols: Vec::new(),
+            event_types: Vec::new(),
+            max_connections: None,
+            max_messages_per_sec: None,
+        }
+    }
+
+    fn request(uri: &str, header: Option<(&str, &str)>) -> Request {
+        let mut builder = Request::builder().uri(uri);
+        if let Some((name, value)) = header {
+            builder = builder.header(name, value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_admit() {
+        let auth = Auth::load(&AuthConfig {
+            keys: vec![ApiKeyConfig {
+                max_connections: Some(1),
+                ..key("secret")
+            }],
+            keys_file: None,
+        })
+        .unwrap();
+
+        assert!(matches!(auth.admit(&request("/", None)), Err(Rejection::Unauthorized)));
+        assert!(matches!(auth.admit(&request("/?api_key=wrong", None)), Err(Rejection::Unauthorized)));
+
+        let grant = auth.admit(&request("/", Some(("Authorization", "Bearer secret")))).unwrap();
+        assert!(matches!(
+            auth.admit(&request("/?api_key=secret", None)),
+            Err(Rejection::TooManyConnections { limit: 1, .. })
+        ));
+        drop(grant);
+        assert!(auth.admit(&request("/?keys=base58&api_key=secret", None)).is_ok());
+        assert!(auth.admit(&request("/", Some(("X-API-Key", "secret")))).is_ok());
+
+        assert!(auth.recognizes(&request("/metrics?api_key=secret", None)));
+        assert!(!auth.recognizes(&request("/metrics", None)));
+    }
+
+    #[test]
+    fn test_entitlements() {
+        let config = ApiKeyConfig {
+            protocols: vec!["pumpfun".to_string()],
+            event_types: vec!["PumpFunTrade".to_string(), "PumpSwapBuy".to_string()],
+            max_messages_per_sec: Some(2),
+            ..key("secret")
+        };
+        let key = ApiKey::new(&config, 0).unwrap();
+        assert_eq!(key.allowed.names(), vec!["PumpFunTrade"]);
+        assert!(key.allow_message() && key.allow_message());
+        assert!(!key.allow_message());
+
+        let unknown = ApiKeyConfig {
+            protocols: vec!["uniswap".to_string()],
+            ..config
+        };
+        assert!(ApiKey::new(&unknown, 0).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+use crate::config::SlowConsumerPolicy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// How far control replies may run past the queue capacity before a client
+/// that keeps sending requests without reading is disconnected
+const CONTROL_HEADROOM: usize = 64;
+
+/// What the sending side of a client should do next
+#[derive(Debug)]
+pub enum Delivery<T> {
+    Message(T),
+    /// `dropped` messages were discarded since the last delivery
+    Lagged { dropped: u64, total_dropped: u64 },
+    /// `dropped` messages were withheld by the client's message rate limit
+    RateLimited { dropped: u64 },
+    /// The slow-consumer policy asked for the connection to be closed
+    Disconnect { lag: usize },
+    /// The queue was closed by the connection owner
+    Closed,
+    /// Everything queued before `drain` has been delivered
+    Drained,
+}
+
+/// Result of offering a message to a client queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    Dropped,
+    Closed,
+}
+
+/// A bounded single-consumer queue with a slow-consumer policy.
+///
+/// Unlike `tokio::sync::mpsc`, a full queue can evict its oldest entry, which
+/// is what a live market feed wants: stale events are worth less than new ones.
+/// Events that are dropped or rate limited leave a gap marker where they
+/// would have been, so the client is told about the loss at the point in the
+/// stream where it happened. Replayed events go ahead of everything queued
+/// and are not subject to the policy; they are bounded by the replay buffer.
+pub struct ClientQueue<T> {
+    state: Mutex<State<T>>,
+    notify: Notify,
+    capacity: usize,
+    lag_threshold: usize,
+}
+
+enum Slot<T> {
+    Event(T),
+    Control(T),
+    /// Adjacent gaps are merged, so there is at most one between two messages
+    Gap { dropped: u64, limited: u64 },
+}
+
+struct State<T> {
+    /// Sent before `slots`
+    replayed: VecDeque<T>,
+    slots: VecDeque<Slot<T>>,
+    /// Number of `Slot::Event`s, which is what the capacity limits
+    events: usize,
+    controls: usize,
+    policy: SlowConsumerPolicy,
+    total_dropped: u64,
+    disconnect_lag: Option<usize>,
+    /// Live delivery is paused while a replay is prepared
+    held: bool,
+    delivered_events: bool,
+    draining: bool,
+    closed: bool,
+}
+
+impl<T> ClientQueue<T> {
+    pub fn new(capacity: usize, lag_threshold: usize, policy: SlowConsumerPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            state: Mutex::new(State {
+                replayed: VecDeque::new(),
+                slots: VecDeque::with_capacity(capacity.min(1024)),
+                events: 0,
+                controls: 0,
+                policy,
+                total_dropped: 0,
+                disconnect_lag: None,
+                held: false,
+                delivered_events: false,
+                draining: false,
+                closed: false,
+            }),
+            notify: Notify::new(),
+            capacity,
+            lag_threshold: lag_threshold.clamp(1, capacity),
+        }
+    }
+
+    pub fn set_policy(&self, policy: SlowConsumerPolicy) {
+        self.state.lock().unwrap().policy = policy;
+    }
+
+    pub fn policy(&self) -> SlowConsumerPolicy {
+        self.state.lock().unwrap().policy
+    }
+
+    /// Number of messages waiting to be sent
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.replayed.len() + state.events + state.controls
+    }
+
+    /// Messages dropped by the slow-consumer policy since the queue was created
+    pub fn total_dropped(&self) -> u64 {
+        self.state.lock().unwrap().total_dropped
+    }
+
+    /// Whether any live event has been handed to the sending side yet
+    pub fn has_delivered_events(&self) -> bool {
+        self.state.lock().unwrap().delivered_events
+    }
+
+    /// Stop delivering queued messages until `release`; they keep queueing
+    /// under the slow-consumer policy meanwhile
+    pub fn hold(&self) {
+        self.state.lock().unwrap().held = true;
+    }
+
+    /// Send `replayed` ahead of everything queued and resume delivery
+    pub fn release(&self, replayed: impl IntoIterator<Item = T>) {
+        let mut state = self.state.lock().unwrap();
+        state.replayed.extend(replayed);
+        state.held = false;
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    /// Offer an event to the queue, applying the slow-consumer policy when full
+    pub fn push(&self, item: T) -> PushOutcome {
+        let mut state = self.state.lock().unwrap();
+        if state.closed || state.disconnect_lag.is_some() {
+            return PushOutcome::Closed;
+        }
+
+        let outcome = match state.policy {
+            SlowConsumerPolicy::DropOldest if state.events >= self.capacity => {
+                state.drop_oldest();
+                state.push_event(item);
+                PushOutcome::Dropped
+            }
+            SlowConsumerPolicy::DropNewest if state.events >= self.capacity => {
+                state.total_dropped += 1;
+                state.gap_at_back(1, 0);
+                PushOutcome::Dropped
+            }
+            SlowConsumerPolicy::Disconnect if state.events >= self.lag_threshold => {
+                state.disconnect_lag = Some(state.events);
+                PushOutcome::Closed
+            }
+            _ => {
+                state.push_event(item);
+                PushOutcome::Queued
+            }
+        };
+
+        drop(state);
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// Queue a control reply; these bypass the slow-consumer policy so a
+    /// client never misses the answer to its own request, but only up to
+    /// `CONTROL_HEADROOM` past the capacity
+    pub fn push_control(&self, item: T) -> PushOutcome {
+        let mut state = self.state.lock().unwrap();
+        if state.closed || state.disconnect_lag.is_some() {
+            return PushOutcome::Closed;
+        }
+
+        let queued = state.events + state.controls;
+        let outcome = if queued >= self.capacity + CONTROL_HEADROOM {
+            state.disconnect_lag = Some(queued);
+            PushOutcome::Closed
+        } else {
+            state.slots.push_back(Slot::Control(item));
+            state.controls += 1;
+            PushOutcome::Queued
+        };
+
+        drop(state);
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// Count a message the client's rate limit kept out of the queue
+    pub fn record_rate_limited(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        state.gap_at_back(0, 1);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+
+    /// Deliver what is queued, then report `Drained` instead of waiting
+    pub fn drain(&self) {
+        self.state.lock().unwrap().draining = true;
+        self.notify.notify_one();
+    }
+
+    /// Wait for the next delivery. Must only be called from one task.
+    pub async fn pop(&self) -> Delivery<T> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(lag) = state.disconnect_lag {
+                    return Delivery::Disconnect { lag };
+                }
+                if let Some(item) = state.replayed.pop_front() {
+                    return Delivery::Message(item);
+                }
+                let next = if state.held { None } else { state.slots.pop_front() };
+                match next {
+                    Some(Slot::Event(item)) => {
+                        state.events -= 1;
+                        state.delivered_events = true;
+                        return Delivery::Message(item);
+                    }
+                    Some(Slot::Control(item)) => {
+                        state.controls -= 1;
+                        return Delivery::Message(item);
+                    }
+                    Some(Slot::Gap { dropped, limited }) if dropped > 0 => {
+                        if limited > 0 {
+                            state.slots.push_front(Slot::Gap { dropped: 0, limited });
+                        }
+                        return Delivery::Lagged { dropped, total_dropped: state.total_dropped };
+                    }
+                    Some(Slot::Gap { limited, .. }) => {
+                        return Delivery::RateLimited { dropped: limited };
+                    }
+                    None if state.closed => return Delivery::Closed,
+                    None if state.draining && !state.held => return Delivery::Drained,
+                    None => {}
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+impl<T> State<T> {
+    fn push_event(&mut self, item: T) {
+        self.slots.push_back(Slot::Event(item));
+        self.events += 1;
+    }
+
+    /// Replace the oldest event with a gap, merged into a gap next to it
+    fn drop_oldest(&mut self) {
+        let Some(index) = self.slots.iter().position(|slot| matches!(slot, Slot::Event(_))) else {
+            return;
+        };
+        self.events -= 1;
+        self.total_dropped += 1;
+
+        let merge_into = if index > 0 && matches!(self.slots[index - 1], Slot::Gap { .. }) {
+            Some(index - 1)
+        } else if matches!(self.slots.get(index + 1), Some(Slot::Gap { .. })) {
+            Some(index + 1)
+        } else {
+            None
+        };
+        match merge_into {
+            Some(gap) => {
+                if let Slot::Gap { dropped, .. } = &mut self.slots[gap] {
+                    *dropped += 1;
+                }
+                self.slots.remove(index);
+            }
+            None => self.slots[index] = Slot::Gap { dropped: 1, limited: 0 },
+        }
+    }
+
+    fn gap_at_back(&mut self, dropped: u64, limited: u64) {
+        if let Some(Slot::Gap { dropped: d, limited: l }) = self.slots.back_mut() {
+            *d += dropped;
+            *l += limited;
+        } else {
+            self.slots.push_back(Slot::Gap { dropped, limited });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let queue = ClientQueue::new(2, 2, SlowConsumerPolicy::DropOldest);
+        assert_eq!(queue.push(1), PushOutcome::Queued);
+        assert_eq!(queue.push(2), PushOutcome::Queued);
+        assert_eq!(queue.push(3), PushOutcome::Dropped);
+
+        assert!(matches!(queue.pop().await, Delivery::Lagged { dropped: 1, total_dropped: 1 }));
+        assert!(matches!(queue.pop().await, Delivery::Message(2)));
+        assert!(matches!(queue.pop().await, Delivery::Message(3)));
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let queue = ClientQueue::new(2, 2, SlowConsumerPolicy::DropNewest);
+        queue.push(1);
+        queue.push(2);
+        assert_eq!(queue.push(3), PushOutcome::Dropped);
+
+        // The notice comes where the dropped message would have been
+        assert!(matches!(queue.pop().await, Delivery::Message(1)));
+        assert!(matches!(queue.pop().await, Delivery::Message(2)));
+        assert!(matches!(queue.pop().await, Delivery::Lagged { dropped: 1, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_gaps_keep_their_position() {
+        let queue = ClientQueue::new(2, 2, SlowConsumerPolicy::DropOldest);
+        queue.push(1);
+        queue.record_rate_limited();
+        queue.push(2);
+        assert_eq!(queue.push(3), PushOutcome::Dropped);
+        assert_eq!(queue.push(4), PushOutcome::Dropped);
+
+        // 1 and 2 were evicted; both merge with the rate limit gap between them
+        assert!(matches!(queue.pop().await, Delivery::Lagged { dropped: 2, total_dropped: 2 }));
+        assert!(matches!(queue.pop().await, Delivery::RateLimited { dropped: 1 }));
+        assert!(matches!(queue.pop().await, Delivery::Message(3)));
+        assert!(matches!(queue.pop().await, Delivery::Message(4)));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_notice() {
+        let queue = ClientQueue::new(2, 2, SlowConsumerPolicy::DropOldest);
+        queue.record_rate_limited();
+        queue.record_rate_limited();
+        queue.push(1);
+
+        assert!(matches!(queue.pop().await, Delivery::RateLimited { dropped: 2 }));
+        assert!(matches!(queue.pop().await, Delivery::Message(1)));
+    }
+
+    #[tokio::test]
+    async fn test_replay_goes_ahead_of_live_events() {
+        let queue = ClientQueue::new(1, 1, SlowConsumerPolicy::Disconnect);
+        queue.hold();
+        queue.push(10);
+        assert!(!queue.has_delivered_events());
+
+        // Replayed messages don't count towards the lag threshold
+        queue.release([1, 2, 3]);
+        for expected in [1, 2, 3, 10] {
+            assert!(matches!(queue.pop().await, Delivery::Message(n) if n == expected));
+        }
+        assert!(queue.has_delivered_events());
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let queue = ClientQueue::new(4, 4, SlowConsumerPolicy::DropOldest);
+        queue.push(1);
+        queue.drain();
+        assert!(matches!(queue.pop().await, Delivery::Message(1)));
+        assert!(matches!(queue.pop().await, Delivery::Drained));
+    }
+
+    #[tokio::test]
+    async fn test_control_backlog_is_bounded() {
+        let queue = ClientQueue::new(2, 2, SlowConsumerPolicy::DropOldest);
+        for i in 0..2 + CONTROL_HEADROOM {
+            assert_eq!(queue.push_control(i), PushOutcome::Queued);
+        }
+        assert_eq!(queue.push_control(0), PushOutcome::Closed);
+        assert!(matches!(queue.pop().await, Delivery::Disconnect { lag } if lag == 2 + CONTROL_HEADROOM));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_past_threshold() {
+        let queue = ClientQueue::new(10, 2, SlowConsumerPolicy::Disconnect);
+        queue.push(1);
+        queue.push(2);
+        assert_eq!(queue.push(3), PushOutcome::Closed);
+        assert!(matches!(queue.pop().await, Delivery::Disconnect { lag: 2 }));
+    }
+}
@@ -1,213 +1,758 @@
-use anyhow::{Context, Result};
+use crate::event_types::{
+    event_config_key, event_type_name, match_event_types, protocol_name, protocol_names, protocol_program_id, EventTypeSet,
+    EVENT_TYPES,
+};
+use anyhow::{bail, Context, Result};
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
+use sol_parser_sdk::grpc::EventType;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::path::Path;
 
+/// Sections and keys left out of a config file take their values from
+/// `Config::default()`, except that protocols left out are off
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub server: ServerConfig,
+    #[serde(default)]
     pub grpc: GrpcConfig,
+    #[serde(default)]
     pub protocols: ProtocolsConfig,
+    #[serde(default)]
     pub events: EventsConfig,
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Maximum number of messages buffered per client
+    #[serde(default = "default_client_queue_capacity")]
+    pub client_queue_capacity: usize,
+    /// What to do when a client's queue is full; clients may override it
+    #[serde(default)]
+    pub slow_consumer_policy: SlowConsumerPolicy,
+    /// Queue depth at which the `disconnect` policy closes the client,
+    /// defaults to `client_queue_capacity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lag_threshold: Option<usize>,
+    /// Offer the `NormalizedTrade` stream next to the raw events
+    #[serde(default)]
+    pub normalize_trades: bool,
+    /// Number of recent events kept for `resume`; 0 disables replay
+    #[serde(default)]
+    pub replay_capacity: usize,
+    /// Also drop replay events older than this; 0 keeps them until
+    /// `replay_capacity` pushes them out
+    #[serde(default)]
+    pub replay_window_ms: u64,
+    /// How long a shutdown waits for clients to receive their queued
+    /// messages before dropping their connections
+    #[serde(default = "default_shutdown_drain_ms")]
+    pub shutdown_drain_ms: u64,
+    /// How long a new connection gets to finish the TLS handshake and send
+    /// its request before it is closed; 0 waits forever
+    #[serde(default = "default_handshake_timeout_ms")]
+    pub handshake_timeout_ms: u64,
+    /// How often the config file is checked for `[events]` and `[protocols]`
+    /// changes; 0 reloads on SIGHUP only
+    #[serde(default = "default_config_reload_interval_ms")]
+    pub config_reload_interval_ms: u64,
+    /// Serve `wss://` with this certificate instead of plain `ws://`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    /// Require an API key during the WebSocket upgrade
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
 }
 
+impl ServerConfig {
+    pub fn lag_threshold(&self) -> usize {
+        self.lag_threshold
+            .unwrap_or(self.client_queue_capacity)
+            .min(self.client_queue_capacity)
+    }
+}
+
+fn default_client_queue_capacity() -> usize {
+    4096
+}
+
+fn default_shutdown_drain_ms() -> u64 {
+    5_000
+}
+
+fn default_handshake_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_config_reload_interval_ms() -> u64 {
+    0
+}
+
+/// PEM certificate chain and private key for the WebSocket listener
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    /// How often the files are checked for changes
+    #[serde(default = "default_tls_reload_interval_ms")]
+    pub reload_interval_ms: u64,
+}
+
+fn default_tls_reload_interval_ms() -> u64 {
+    10_000
+}
+
+/// API keys accepted by the WebSocket listener, from the config itself
+/// and/or a separate keys file with the same `[[keys]]` entries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<ApiKeyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keys_file: Option<String>,
+}
+
+/// One API key and what it entitles its clients to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    /// Shown in logs instead of the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Protocols whose events the key may receive, e.g. `pumpfun`; empty allows all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<String>,
+    /// Event types the key may receive; empty allows all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event_types: Vec<String>,
+    /// Concurrent connections allowed with this key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
+    /// Events delivered per second across all of the key's connections
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages_per_sec: Option<u64>,
+}
+
+/// How a client's bounded queue behaves once it falls behind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowConsumerPolicy {
+    /// Evict the oldest queued message to make room for the new one
+    #[default]
+    DropOldest,
+    /// Discard the new message and keep the queue as it is
+    DropNewest,
+    /// Close the connection once the lag reaches the threshold
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GrpcConfig {
     pub endpoint: String,
     pub token: Option<String>,
+    /// Deprecated and ignored; the proxy's metrics are served on `/metrics`.
+    /// Kept so existing config files still load.
     pub enable_metrics: bool,
     pub enable_tls: bool,
+    /// Limit on connecting to an endpoint, 0 for none
     pub connection_timeout_ms: u64,
+    /// Limit on the subscribe request once connected, 0 for none; the
+    /// stream itself is watched by `stall_timeout_ms`
     pub request_timeout_ms: u64,
+    /// First reconnect delay; doubles on every failed attempt
+    #[serde(default = "default_reconnect_initial_delay_ms")]
+    pub reconnect_initial_delay_ms: u64,
+    #[serde(default = "default_reconnect_max_delay_ms")]
+    pub reconnect_max_delay_ms: u64,
+    /// Resubscribe after this long without a single event, 0 to only
+    /// resubscribe when the stream fails
+    #[serde(default = "default_stall_timeout_ms")]
+    pub stall_timeout_ms: u64,
+    /// How multiple `endpoints` are used
+    #[serde(default)]
+    pub mode: FeedMode,
+    /// Extra endpoints; when set, these replace `endpoint`/`token`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<EndpointConfig>,
+    /// Number of recent events remembered to drop duplicates in `race` mode
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: usize,
+}
+
+impl GrpcConfig {
+    /// The endpoints to connect to, falling back to the single `endpoint`
+    pub fn endpoints(&self) -> Vec<EndpointConfig> {
+        if !self.endpoints.is_empty() {
+            return self.endpoints.clone();
+        }
+        vec![EndpointConfig {
+            endpoint: self.endpoint.clone(),
+            token: self.token.clone(),
+            enable_tls: None,
+        }]
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProtocolsConfig {
-    pub pumpfun: bool,
-    pub pumpswap: bool,
-    pub bonk: bool,
-    pub raydium_amm_v4: bool,
-    pub raydium_clmm: bool,
-    pub raydium_cpmm: bool,
+pub struct EndpointConfig {
+    pub endpoint: String,
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Defaults to `grpc.enable_tls`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_tls: Option<bool>,
+}
+
+/// How the proxy uses several gRPC endpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedMode {
+    /// Use one endpoint at a time and move to the next when it fails
+    #[default]
+    Failover,
+    /// Subscribe to every endpoint at once and forward whichever copy of an
+    /// event arrives first
+    Race,
+}
+
+fn default_dedup_window() -> usize {
+    65_536
+}
+
+fn default_reconnect_initial_delay_ms() -> u64 {
+    500
+}
+
+fn default_reconnect_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_stall_timeout_ms() -> u64 {
+    0
+}
+
+/// How the consumer waits when the parsed-event queue is empty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitStrategy {
+    /// Spin on a dedicated thread; lowest latency, keeps one core at 100%
+    BusySpin,
+    /// Spin for `spin_iterations`, then park the thread for `park_timeout_us`
+    #[default]
+    SpinThenPark,
+    /// Run on the tokio runtime and sleep `park_timeout_us` between polls;
+    /// tokio timers round anything under 1ms up to 1ms
+    Poll,
+    /// Run on the tokio runtime and sleep until the gRPC stream queues an
+    /// event
+    Notify,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EventsConfig {
-    pub block_meta: bool,
-    pub bonk_trade: bool,
-    pub bonk_pool_create: bool,
-    pub bonk_migrate_amm: bool,
-    pub pumpfun_trade: bool,
-    pub pumpfun_create: bool,
-    pub pumpfun_complete: bool,
-    pub pumpfun_migrate: bool,
-    pub pumpswap_buy: bool,
-    pub pumpswap_sell: bool,
-    pub pumpswap_create_pool: bool,
-    pub pumpswap_pool_created: bool,
-    pub pumpswap_trade: bool,
-    pub pumpswap_liquidity_added: bool,
-    pub pumpswap_liquidity_removed: bool,
-    pub pumpswap_pool_updated: bool,
-    pub pumpswap_fees_claimed: bool,
-    pub raydium_cpmm_swap: bool,
-    pub raydium_cpmm_deposit: bool,
-    pub raydium_cpmm_withdraw: bool,
-    pub raydium_cpmm_initialize: bool,
-    pub raydium_clmm_swap: bool,
-    pub raydium_clmm_create_pool: bool,
-    pub raydium_clmm_open_position: bool,
-    pub raydium_clmm_close_position: bool,
-    pub raydium_clmm_increase_liquidity: bool,
-    pub raydium_clmm_decrease_liquidity: bool,
-    pub raydium_clmm_open_position_with_token_ext_nft: bool,
-    pub raydium_clmm_collect_fee: bool,
-    pub raydium_amm_v4_swap: bool,
-    pub raydium_amm_v4_deposit: bool,
-    pub raydium_amm_v4_withdraw: bool,
-    pub raydium_amm_v4_initialize2: bool,
-    pub raydium_amm_v4_withdraw_pnl: bool,
-    pub orca_whirlpool_swap: bool,
-    pub orca_whirlpool_liquidity_increased: bool,
-    pub orca_whirlpool_liquidity_decreased: bool,
-    pub orca_whirlpool_pool_initialized: bool,
-    pub meteora_pools_swap: bool,
-    pub meteora_pools_add_liquidity: bool,
-    pub meteora_pools_remove_liquidity: bool,
-    pub meteora_pools_bootstrap_liquidity: bool,
-    pub meteora_pools_pool_created: bool,
-    pub meteora_pools_set_pool_fees: bool,
-    pub meteora_damm_v2_swap: bool,
-    pub meteora_damm_v2_add_liquidity: bool,
-    pub meteora_damm_v2_remove_liquidity: bool,
-    pub meteora_damm_v2_initialize_pool: bool,
-    pub meteora_damm_v2_create_position: bool,
-    pub meteora_damm_v2_close_position: bool,
-    pub meteora_damm_v2_claim_position_fee: bool,
-    pub meteora_damm_v2_initialize_reward: bool,
-    pub meteora_damm_v2_fund_reward: bool,
-    pub meteora_damm_v2_claim_reward: bool,
-    pub token_account: bool,
-    pub nonce_account: bool,
-    pub token_info: bool,
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub wait_strategy: WaitStrategy,
+    #[serde(default = "default_spin_iterations")]
+    pub spin_iterations: u32,
+    #[serde(default = "default_park_timeout_us")]
+    pub park_timeout_us: u64,
+    /// Pin the consumer thread to this core (`busy_spin` and `spin_then_park`
+    /// only; ignored in `race` mode, where every endpoint has a consumer)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_core: Option<usize>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            wait_strategy: WaitStrategy::default(),
+            spin_iterations: default_spin_iterations(),
+            park_timeout_us: default_park_timeout_us(),
+            cpu_core: None,
+        }
+    }
+}
+
+fn default_spin_iterations() -> u32 {
+    1000
+}
+
+fn default_park_timeout_us() -> u64 {
+    100
+}
+
+/// Protocols subscribed to even without an enabled event type of theirs.
+/// `None` leaves a protocol to follow the events; `Some(false)` switches it
+/// off, and enabling its events is a config problem.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtocolsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pumpfun: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pumpswap: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bonk: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raydium_amm_v4: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raydium_clmm: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raydium_cpmm: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orca_whirlpool: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meteora_pools: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meteora_damm_v2: Option<bool>,
+}
+
+impl ProtocolsConfig {
+    /// Each protocol's `[protocols]` key and its toggle
+    fn toggles(&self) -> [(&'static str, Option<bool>); 9] {
+        [
+            ("pumpfun", self.pumpfun),
+            ("pumpswap", self.pumpswap),
+            ("bonk", self.bonk),
+            ("raydium_amm_v4", self.raydium_amm_v4),
+            ("raydium_clmm", self.raydium_clmm),
+            ("raydium_cpmm", self.raydium_cpmm),
+            ("orca_whirlpool", self.orca_whirlpool),
+            ("meteora_pools", self.meteora_pools),
+            ("meteora_damm_v2", self.meteora_damm_v2),
+        ]
+    }
+
+    /// Whether `protocol` is set to `false` rather than left out
+    pub fn is_switched_off(&self, protocol: &str) -> bool {
+        self.toggles().contains(&(protocol, Some(false)))
+    }
+}
+
+/// The event types to stream, either as a list of wire names and `*`
+/// patterns, `events = ["PumpFunTrade", "RaydiumClmm*", "Meteora*Swap"]`,
+/// or as the older `[events]` table with one flag per event type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum EventsConfig {
+    Patterns(Vec<String>),
+    /// Keyed by `event_types::event_config_key`, e.g. `pumpfun_trade = true`
+    Flags(BTreeMap<String, bool>),
+}
+
+impl EventsConfig {
+    /// Whether `event_type` is streamed; replaces reading the per-type
+    /// fields of the former `EventsConfig` struct, e.g. `events.pumpfun_trade`
+    pub fn is_enabled(&self, event_type: EventType) -> bool {
+        self.event_types().contains(event_type)
+    }
+
+    /// Switch one event type on or off; replaces assigning the per-type
+    /// fields of the former `EventsConfig` struct, e.g.
+    /// `events.pumpfun_trade = true`
+    pub fn set_enabled(&mut self, event_type: EventType, enabled: bool) {
+        let mut event_types = self.event_types();
+        match enabled {
+            true => event_types.insert(event_type),
+            false => event_types.remove(event_type),
+        }
+        match self {
+            Self::Patterns(patterns) => {
+                *patterns = event_types.names().into_iter().map(str::to_string).collect();
+            }
+            Self::Flags(flags) => {
+                flags.insert(event_config_key(event_type), enabled);
+            }
+        }
+    }
+
+    pub fn event_types(&self) -> EventTypeSet {
+        match self {
+            Self::Patterns(patterns) => patterns
+                .iter()
+                .fold(EventTypeSet::empty(), |set, pattern| set.union(match_event_types(pattern))),
+            Self::Flags(flags) => EVENT_TYPES
+                .iter()
+                .map(|(_, t)| *t)
+                .filter(|t| flags.get(&event_config_key(*t)) == Some(&true))
+                .collect(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EventsConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct EventsVisitor;
+
+        impl<'de> Visitor<'de> for EventsVisitor {
+            type Value = EventsConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of event type patterns or an [events] table")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> std::result::Result<Self::Value, A::Error> {
+                let patterns: Vec<String> = Deserialize::deserialize(SeqAccessDeserializer::new(seq))?;
+                if let Some(pattern) = patterns.iter().find(|p| match_event_types(p).is_empty()) {
+                    return Err(de::Error::custom(format!(
+                        "event pattern {:?} matches no event type",
+                        pattern
+                    )));
+                }
+                Ok(EventsConfig::Patterns(patterns))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Self::Value, A::Error> {
+                Deserialize::deserialize(MapAccessDeserializer::new(map)).map(EventsConfig::Flags)
+            }
+        }
+
+        deserializer.deserialize_any(EventsVisitor)
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 9001,
+            client_queue_capacity: default_client_queue_capacity(),
+            slow_consumer_policy: SlowConsumerPolicy::default(),
+            lag_threshold: None,
+            normalize_trades: false,
+            replay_capacity: 0,
+            replay_window_ms: 0,
+            shutdown_drain_ms: default_shutdown_drain_ms(),
+            handshake_timeout_ms: default_handshake_timeout_ms(),
+            config_reload_interval_ms: default_config_reload_interval_ms(),
+            tls: None,
+            auth: None,
+        }
+    }
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "https://solana-yellowstone-grpc.publicnode.com:443".to_string(),
+            token: None,
+            enable_metrics: true,
+            enable_tls: true,
+            connection_timeout_ms: 10000,
+            request_timeout_ms: 30000,
+            reconnect_initial_delay_ms: default_reconnect_initial_delay_ms(),
+            reconnect_max_delay_ms: default_reconnect_max_delay_ms(),
+            stall_timeout_ms: default_stall_timeout_ms(),
+            mode: FeedMode::default(),
+            endpoints: Vec::new(),
+            dedup_window: default_dedup_window(),
+        }
+    }
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self::Patterns(vec!["PumpFunTrade".to_string(), "PumpFunCreate".to_string()])
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            server: ServerConfig {
-                host: "127.0.0.1".to_string(),
-                port: 9001,
-            },
-            grpc: GrpcConfig {
-                endpoint: "https://solana-yellowstone-grpc.publicnode.com:443".to_string(),
-                token: None,
-                enable_metrics: true,
-                enable_tls: true,
-                connection_timeout_ms: 10000,
-                request_timeout_ms: 30000,
-            },
+            server: ServerConfig::default(),
+            grpc: GrpcConfig::default(),
             protocols: ProtocolsConfig {
-                pumpfun: true,
-                pumpswap: false,
-                bonk: false,
-                raydium_amm_v4: false,
-                raydium_clmm: false,
-                raydium_cpmm: false,
-            },
-            events: EventsConfig {
-                block_meta: false,
-                bonk_trade: false,
-                bonk_pool_create: false,
-                bonk_migrate_amm: false,
-                pumpfun_trade: true,
-                pumpfun_create: true,
-                pumpfun_complete: false,
-                pumpfun_migrate: false,
-                pumpswap_buy: false,
-                pumpswap_sell: false,
-                pumpswap_create_pool: false,
-                pumpswap_pool_created: false,
-                pumpswap_trade: false,
-                pumpswap_liquidity_added: false,
-                pumpswap_liquidity_removed: false,
-                pumpswap_pool_updated: false,
-                pumpswap_fees_claimed: false,
-                raydium_cpmm_swap: false,
-                raydium_cpmm_deposit: false,
-                raydium_cpmm_withdraw: false,
-                raydium_cpmm_initialize: false,
-                raydium_clmm_swap: false,
-                raydium_clmm_create_pool: false,
-                raydium_clmm_open_position: false,
-                raydium_clmm_close_position: false,
-                raydium_clmm_increase_liquidity: false,
-                raydium_clmm_decrease_liquidity: false,
-                raydium_clmm_open_position_with_token_ext_nft: false,
-                raydium_clmm_collect_fee: false,
-                raydium_amm_v4_swap: false,
-                raydium_amm_v4_deposit: false,
-                raydium_amm_v4_withdraw: false,
-                raydium_amm_v4_initialize2: false,
-                raydium_amm_v4_withdraw_pnl: false,
-                orca_whirlpool_swap: false,
-                orca_whirlpool_liquidity_increased: false,
-                orca_whirlpool_liquidity_decreased: false,
-                orca_whirlpool_pool_initialized: false,
-                meteora_pools_swap: false,
-                meteora_pools_add_liquidity: false,
-                meteora_pools_remove_liquidity: false,
-                meteora_pools_bootstrap_liquidity: false,
-                meteora_pools_pool_created: false,
-                meteora_pools_set_pool_fees: false,
-                meteora_damm_v2_swap: false,
-                meteora_damm_v2_add_liquidity: false,
-                meteora_damm_v2_remove_liquidity: false,
-                meteora_damm_v2_initialize_pool: false,
-                meteora_damm_v2_create_position: false,
-                meteora_damm_v2_close_position: false,
-                meteora_damm_v2_claim_position_fee: false,
-                meteora_damm_v2_initialize_reward: false,
-                meteora_damm_v2_fund_reward: false,
-                meteora_damm_v2_claim_reward: false,
-                token_account: false,
-                nonce_account: false,
-                token_info: false,
+                pumpfun: Some(true),
+                ..ProtocolsConfig::default()
             },
+            events: EventsConfig::default(),
+            pipeline: PipelineConfig::default(),
         }
     }
 }
 
 impl Config {
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
-
-        let config: Config = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse config file: {:?}", path.as_ref()))?;
+        Self::load(path).map(|(config, _)| config)
+    }
 
+    /// Load a config file and refuse it if `problems` finds anything wrong
+    pub fn load_strict<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let (config, problems) = Self::load(&path)?;
+        if !problems.is_empty() {
+            bail!("Invalid config file {:?}:\n  - {}", path.as_ref(), problems.join("\n  - "));
+        }
         Ok(config)
     }
 
     pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
-        match Self::load_from_file(&path) {
-            Ok(config) => {
+        match Self::load(&path) {
+            Ok((config, problems)) => {
                 tracing::info!("✅ Loaded config from: {:?}", path.as_ref());
+                for problem in problems {
+                    tracing::warn!("⚠️  Config: {}", problem);
+                }
                 config
             }
             Err(e) => {
-                tracing::warn!("⚠️  Failed to load config: {}, using default", e);
+                tracing::warn!("⚠️  Failed to load config: {:#}, using default", e);
                 Self::default()
             }
         }
     }
 
+    /// The parsed config with what `problems` found in it
+    fn load<P: AsRef<Path>>(path: P) -> Result<(Self, Vec<String>)> {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
+
+        let raw: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {:?}", path.as_ref()))?;
+        let config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {:?}", path.as_ref()))?;
+
+        let problems = config.problems(Some(&raw))?;
+        Ok((config, problems))
+    }
+
+    /// Fail if `problems` finds anything wrong, for configs built in code
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems(None)?;
+        if !problems.is_empty() {
+            bail!("Invalid config:\n  - {}", problems.join("\n  - "));
+        }
+        Ok(())
+    }
+
+    /// Everything a strict load refuses: keys that don't configure anything,
+    /// listener conflicts, and events of a protocol switched off in
+    /// `[protocols]`. Only unknown keys need `raw`, the TOML the config was
+    /// read from; the other checks run on the config itself.
+    fn problems(&self, raw: Option<&toml::Table>) -> Result<Vec<String>> {
+        let mut problems = match raw {
+            Some(raw) => self.unknown_keys(raw)?,
+            None => self.unknown_event_flags(),
+        };
+
+        problems.extend(self.listener_conflicts());
+
+        for event_type in self.events.event_types().iter() {
+            let protocol = protocol_name(event_type);
+            if self.protocols.is_switched_off(protocol) {
+                problems.push(format!(
+                    "event {} is enabled but protocol `{}` is switched off",
+                    event_type_name(event_type),
+                    protocol
+                ));
+            }
+        }
+
+        Ok(problems)
+    }
+
+    /// Settings that clash over a listener. `/healthz`, `/readyz`, `/info`,
+    /// `/metrics` and TLS are all served on the WebSocket listener, so
+    /// `server.port` is the only port the proxy binds; what can clash with
+    /// it are gRPC endpoints pointing back at it, or at each other.
+    fn listener_conflicts(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let endpoints = self.grpc.endpoints();
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            if self.is_own_listener(&endpoint.endpoint) {
+                problems.push(format!(
+                    "gRPC endpoint {} is the proxy's own listener on port {}",
+                    endpoint.endpoint, self.server.port
+                ));
+            }
+            let earlier = endpoints[..i].iter().filter(|e| e.endpoint == endpoint.endpoint).count();
+            if earlier == 1 {
+                problems.push(format!("gRPC endpoint {} is listed more than once", endpoint.endpoint));
+            }
+        }
+
+        if let Some(tls) = &self.server.tls {
+            for (key, path) in [("cert_path", &tls.cert_path), ("key_path", &tls.key_path)] {
+                if path.trim().is_empty() {
+                    problems.push(format!("`server.tls.{}` is empty", key));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// The keys of `raw`, the TOML this config was read from, that don't
+    /// configure anything
+    fn unknown_keys(&self, raw: &toml::Table) -> Result<Vec<String>> {
+        let known = toml::Table::try_from(self).context("Failed to serialize config to TOML")?;
+        let mut problems = Vec::new();
+        unknown_keys(raw, &known, "", &mut problems);
+        problems.extend(self.unknown_event_flags());
+        Ok(problems)
+    }
+
+    fn unknown_event_flags(&self) -> Vec<String> {
+        let EventsConfig::Flags(flags) = &self.events else {
+            return Vec::new();
+        };
+        let keys: Vec<String> = EVENT_TYPES.iter().map(|(_, t)| event_config_key(*t)).collect();
+        flags
+            .keys()
+            .filter(|key| !keys.contains(key))
+            .map(|key| format!("unknown key `events.{}`", key))
+            .collect()
+    }
+
+    /// Whether a gRPC endpoint URL points at this proxy's WebSocket listener
+    fn is_own_listener(&self, endpoint: &str) -> bool {
+        let (scheme, rest) = endpoint.split_once("://").unwrap_or(("https", endpoint));
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (host, port.parse().ok()),
+            _ => (authority, None),
+        };
+        let port = port.unwrap_or(if scheme == "http" { 80 } else { 443 });
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let local = ["localhost", "127.0.0.1", "::1", "0.0.0.0", "::"];
+        self.server.port != 0
+            && port == self.server.port
+            && (host == self.server.host || (local.contains(&host) && local.contains(&self.server.host.as_str())))
+    }
+
+    /// Override keys given as dotted paths, such as `grpc.token`, `events` or
+    /// `grpc.endpoints.0.token`. Values are read as TOML values, falling back
+    /// to plain strings, and a list can also be given comma-separated, e.g.
+    /// `events` = `PumpFunTrade,RaydiumClmm*`. Unknown keys are refused.
+    pub fn apply_overrides<K, V>(&mut self, overrides: impl IntoIterator<Item = (K, V)>) -> Result<()>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.apply(overrides.into_iter().map(|(key, value)| Override::new(None, key, value)).collect())
+    }
+
+    /// `apply_overrides` for overrides that came from somewhere, such as a
+    /// `--port` flag; errors name the source along with the key
+    pub fn apply_sourced_overrides<S, K, V>(&mut self, overrides: impl IntoIterator<Item = (S, K, V)>) -> Result<()>
+    where
+        S: AsRef<str>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.apply(
+            overrides
+                .into_iter()
+                .map(|(source, key, value)| Override::new(Some(source.as_ref()), key, value))
+                .collect(),
+        )
+    }
+
+    /// Override keys from `PPWS_<SECTION>__<KEY>` environment variables, e.g.
+    /// `PPWS_GRPC__TOKEN` for `grpc.token` or `PPWS_EVENTS` for `events`
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        self.apply_env_overrides_from(std::env::vars())
+    }
+
+    /// `apply_env_overrides` with the variables given as name/value pairs
+    /// instead of read from the process environment
+    pub fn apply_env_overrides_from<K, V>(&mut self, vars: impl IntoIterator<Item = (K, V)>) -> Result<()>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut overrides: Vec<Override> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let key = name.as_ref().strip_prefix(ENV_PREFIX)?.to_lowercase().replace("__", ".");
+                Some(Override::new(Some(name.as_ref()), key, value))
+            })
+            .filter(|o| !o.key.is_empty())
+            .collect();
+        overrides.sort_by(|a, b| a.key.cmp(&b.key));
+        self.apply(overrides)
+    }
+
+    fn apply(&mut self, overrides: Vec<Override>) -> Result<()> {
+        let e = match self.overridden(&overrides) {
+            Ok(config) => {
+                *self = config;
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+        // Blame the override the config is fine without, or all of them
+        let culprit = (0..overrides.len()).find(|&i| {
+            let mut rest = overrides.clone();
+            rest.remove(i);
+            self.overridden(&rest).is_ok()
+        });
+        let blamed: Vec<String> = match culprit {
+            Some(i) => vec![overrides[i].to_string()],
+            None => overrides.iter().map(Override::to_string).collect(),
+        };
+        Err(e.context(format!("Invalid config override {}", blamed.join(", "))))
+    }
+
+    /// This config with `overrides` applied
+    fn overridden(&self, overrides: &[Override]) -> Result<Config> {
+        let mut table = toml::Value::try_from(self).context("Failed to serialize config to TOML")?;
+        // Keys the config leaves out have no type to go by; if reading them
+        // as TOML values doesn't fit, they are retried as strings
+        let mut untyped = Vec::new();
+        for o in overrides {
+            let path: Vec<&str> = o.key.split('.').collect();
+            if !set_path(&mut table, &path, &o.value, false)? {
+                untyped.push((path, o.value.as_str()));
+            }
+        }
+
+        let config: Config = match table.clone().try_into() {
+            Ok(config) => config,
+            Err(_) if !untyped.is_empty() => {
+                for (path, value) in &untyped {
+                    set_path(&mut table, path, value, true)?;
+                }
+                table.clone().try_into()?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let raw = table.as_table().expect("config serializes to a table");
+        if let Some(problem) = config.unknown_keys(raw)?.into_iter().next() {
+            bail!(problem);
+        }
+        Ok(config)
+    }
+
+    /// The config as pretty TOML with gRPC tokens and API keys replaced by
+    /// `<redacted>`, for `--print-config`
+    pub fn to_redacted_toml(&self) -> Result<String> {
+        let mut config = self.clone();
+        let redact = |secret: &mut String| {
+            if !secret.is_empty() {
+                *secret = REDACTED.to_string();
+            }
+        };
+        let tokens = config.grpc.endpoints.iter_mut().map(|e| &mut e.token);
+        for token in std::iter::once(&mut config.grpc.token).chain(tokens).flatten() {
+            redact(token);
+        }
+        for key in config.server.auth.iter_mut().flat_map(|auth| &mut auth.keys) {
+            redact(&mut key.key);
+        }
+        toml::to_string_pretty(&config).context("Failed to serialize config to TOML")
+    }
+
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let content = toml::to_string_pretty(self)
             .context("Failed to serialize config to TOML")?;
@@ -218,94 +763,375 @@ impl Config {
         Ok(())
     }
 
+    /// The enabled protocols that sol-parser-sdk has a `Protocol` for. Orca
+    /// Whirlpool and the Meteora programs have none; `get_enabled_program_ids`
+    /// covers every protocol.
     pub fn get_enabled_protocols(&self) -> Vec<sol_parser_sdk::grpc::Protocol> {
         use sol_parser_sdk::grpc::Protocol;
-        let mut protocols = Vec::new();
+        self.get_enabled_protocol_names()
+            .into_iter()
+            .filter_map(|name| match name {
+                "pumpfun" => Some(Protocol::PumpFun),
+                "pumpswap" => Some(Protocol::PumpSwap),
+                "bonk" => Some(Protocol::Bonk),
+                "raydium_amm_v4" => Some(Protocol::RaydiumAmmV4),
+                "raydium_clmm" => Some(Protocol::RaydiumClmm),
+                "raydium_cpmm" => Some(Protocol::RaydiumCpmm),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `[protocols]` keys of the enabled protocols: those switched on in
+    /// `[protocols]` plus those an enabled event type belongs to
+    pub fn get_enabled_protocol_names(&self) -> Vec<&'static str> {
+        let toggles = self.protocols.toggles();
+        let event_types = self.events.event_types();
+        protocol_names()
+            .filter(|name| {
+                toggles.iter().any(|(toggle, enabled)| *enabled == Some(true) && toggle == name)
+                    || event_types.iter().any(|t| protocol_name(t) == *name)
+            })
+            .collect()
+    }
 
-        if self.protocols.pumpfun {
-            protocols.push(Protocol::PumpFun);
+    /// The programs the gRPC transaction and account filters watch
+    pub fn get_enabled_program_ids(&self) -> Vec<String> {
+        self.get_enabled_protocol_names()
+            .into_iter()
+            .filter_map(protocol_program_id)
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn get_enabled_event_types(&self) -> Vec<sol_parser_sdk::grpc::EventType> {
+        self.events.event_types().iter().collect()
+    }
+}
+const ENV_PREFIX: &str = "PPWS_";
+
+/// One override, with the flag or environment variable it came from
+#[derive(Clone)]
+struct Override {
+    source: Option<String>,
+    key: String,
+    value: String,
+}
+
+impl Override {
+    fn new(source: Option<&str>, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        Self {
+            source: source.map(str::to_string),
+            key: key.as_ref().to_string(),
+            value: value.as_ref().to_string(),
         }
-        if self.protocols.pumpswap {
-            protocols.push(Protocol::PumpSwap);
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "`{}` from {}", self.key, source),
+            None => write!(f, "`{}`", self.key),
         }
-        if self.protocols.bonk {
-            protocols.push(Protocol::Bonk);
+    }
+}
+
+const REDACTED: &str = "<redacted>";
+
+/// Set the value at `path` below `node`, creating missing tables, and
+/// appending to a list when the index is one past its end. `false` when the
+/// key didn't exist yet, so the value's type was a guess.
+fn set_path(node: &mut toml::Value, path: &[&str], raw: &str, as_string: bool) -> Result<bool> {
+    let Some((key, rest)) = path.split_first() else {
+        return Ok(true);
+    };
+    let child = match node {
+        toml::Value::Table(table) => {
+            if rest.is_empty() {
+                let current = table.get(*key);
+                let typed = current.is_some();
+                let value = match as_string {
+                    true => toml::Value::String(raw.to_string()),
+                    false => parse_override(raw, current, *key == "events"),
+                };
+                table.insert(key.to_string(), value);
+                return Ok(typed);
+            }
+            let is_index = rest[0].parse::<usize>().is_ok();
+            table.entry(key.to_string()).or_insert_with(|| match is_index {
+                true => toml::Value::Array(Vec::new()),
+                false => toml::Value::Table(toml::Table::new()),
+            })
         }
-        if self.protocols.raydium_amm_v4 {
-            protocols.push(Protocol::RaydiumAmmV4);
+        toml::Value::Array(list) => {
+            let index: usize = key.parse().with_context(|| format!("`{}` is not a list index", key))?;
+            if index == list.len() {
+                list.push(toml::Value::Table(toml::Table::new()));
+            }
+            let len = list.len();
+            let item = list
+                .get_mut(index)
+                .with_context(|| format!("index {} is past the end of a list of {}", index, len))?;
+            if rest.is_empty() {
+                *item = parse_override(raw, Some(item), false);
+                return Ok(true);
+            }
+            item
         }
-        if self.protocols.raydium_clmm {
-            protocols.push(Protocol::RaydiumClmm);
+        _ => bail!("`{}` is not a table", key),
+    };
+    set_path(child, rest, raw, as_string)
+}
+
+/// An override value as TOML. Strings stay strings even when they look
+/// like numbers, so a numeric token isn't turned into an integer.
+fn parse_override(raw: &str, current: Option<&toml::Value>, list: bool) -> toml::Value {
+    if let Some(toml::Value::String(_)) = current {
+        return toml::Value::String(raw.to_string());
+    }
+    let parsed = toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+    match parsed {
+        Some(value) => value,
+        None if list || matches!(current, Some(toml::Value::Array(_))) => toml::Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| toml::Value::String(item.to_string()))
+                .collect(),
+        ),
+        None => toml::Value::String(raw.to_string()),
+    }
+}
+
+/// Collect the keys of `raw` that are missing from `known`, the loaded
+/// config serialized back, i.e. the keys deserialization ignored
+fn unknown_keys(raw: &toml::Table, known: &toml::Table, prefix: &str, problems: &mut Vec<String>) {
+    for (key, value) in raw {
+        let path = format!("{}{}", prefix, key);
+        match (value, known.get(key)) {
+            (toml::Value::Table(raw), Some(toml::Value::Table(known))) => {
+                unknown_keys(raw, known, &format!("{}.", path), problems)
+            }
+            (toml::Value::Array(raw), Some(toml::Value::Array(known))) => {
+                for (i, (raw, known)) in raw.iter().zip(known).enumerate() {
+                    if let (toml::Value::Table(raw), toml::Value::Table(known)) = (raw, known) {
+                        unknown_keys(raw, known, &format!("{}[{}].", path, i), problems);
+                    }
+                }
+            }
+            // Empty lists are left out when serializing
+            (toml::Value::Array(list), None) if list.is_empty() => {}
+            (_, None) => problems.push(format!("unknown key `{}`", path)),
+            _ => {}
         }
-        if self.protocols.raydium_cpmm {
-            protocols.push(Protocol::RaydiumCpmm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(toml: &str) -> Result<EventsConfig, toml::de::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            events: EventsConfig,
         }
+        toml::from_str::<Wrapper>(toml).map(|w| w.events)
+    }
+
+    #[test]
+    fn test_event_patterns() {
+        let config = events(r#"events = ["PumpFunTrade", "RaydiumClmm*", "Meteora*Swap"]"#).unwrap();
+        let event_types = config.event_types();
+        assert!(event_types.contains(EventType::PumpFunTrade));
+        assert!(event_types.contains(EventType::RaydiumClmmCollectFee));
+        assert!(event_types.contains(EventType::MeteoraDammV2Swap));
+        assert!(!event_types.contains(EventType::PumpFunCreate));
+        assert_eq!(event_types.iter().count(), 11);
 
-        protocols
+        let err = events(r#"events = ["PumpFunTrade", "PumpFunTrades"]"#).unwrap_err();
+        assert!(err.to_string().contains("\"PumpFunTrades\" matches no event type"), "{}", err);
     }
 
-    pub fn get_enabled_event_types(&self) -> Vec<sol_parser_sdk::grpc::EventType> {
-        use sol_parser_sdk::grpc::EventType;
-        let mut event_types = Vec::new();
-
-        if self.events.block_meta { event_types.push(EventType::BlockMeta); }
-        if self.events.bonk_trade { event_types.push(EventType::BonkTrade); }
-        if self.events.bonk_pool_create { event_types.push(EventType::BonkPoolCreate); }
-        if self.events.bonk_migrate_amm { event_types.push(EventType::BonkMigrateAmm); }
-        if self.events.pumpfun_trade { event_types.push(EventType::PumpFunTrade); }
-        if self.events.pumpfun_create { event_types.push(EventType::PumpFunCreate); }
-        if self.events.pumpfun_complete { event_types.push(EventType::PumpFunComplete); }
-        if self.events.pumpfun_migrate { event_types.push(EventType::PumpFunMigrate); }
-        if self.events.pumpswap_buy { event_types.push(EventType::PumpSwapBuy); }
-        if self.events.pumpswap_sell { event_types.push(EventType::PumpSwapSell); }
-        if self.events.pumpswap_create_pool { event_types.push(EventType::PumpSwapCreatePool); }
-        if self.events.pumpswap_pool_created { event_types.push(EventType::PumpSwapPoolCreated); }
-        if self.events.pumpswap_trade { event_types.push(EventType::PumpSwapTrade); }
-        if self.events.pumpswap_liquidity_added { event_types.push(EventType::PumpSwapLiquidityAdded); }
-        if self.events.pumpswap_liquidity_removed { event_types.push(EventType::PumpSwapLiquidityRemoved); }
-        if self.events.pumpswap_pool_updated { event_types.push(EventType::PumpSwapPoolUpdated); }
-        if self.events.pumpswap_fees_claimed { event_types.push(EventType::PumpSwapFeesClaimed); }
-        if self.events.raydium_cpmm_swap { event_types.push(EventType::RaydiumCpmmSwap); }
-        if self.events.raydium_cpmm_deposit { event_types.push(EventType::RaydiumCpmmDeposit); }
-        if self.events.raydium_cpmm_withdraw { event_types.push(EventType::RaydiumCpmmWithdraw); }
-        if self.events.raydium_cpmm_initialize { event_types.push(EventType::RaydiumCpmmInitialize); }
-        if self.events.raydium_clmm_swap { event_types.push(EventType::RaydiumClmmSwap); }
-        if self.events.raydium_clmm_create_pool { event_types.push(EventType::RaydiumClmmCreatePool); }
-        if self.events.raydium_clmm_open_position { event_types.push(EventType::RaydiumClmmOpenPosition); }
-        if self.events.raydium_clmm_close_position { event_types.push(EventType::RaydiumClmmClosePosition); }
-        if self.events.raydium_clmm_increase_liquidity { event_types.push(EventType::RaydiumClmmIncreaseLiquidity); }
-        if self.events.raydium_clmm_decrease_liquidity { event_types.push(EventType::RaydiumClmmDecreaseLiquidity); }
-        if self.events.raydium_clmm_open_position_with_token_ext_nft { event_types.push(EventType::RaydiumClmmOpenPositionWithTokenExtNft); }
-        if self.events.raydium_clmm_collect_fee { event_types.push(EventType::RaydiumClmmCollectFee); }
-        if self.events.raydium_amm_v4_swap { event_types.push(EventType::RaydiumAmmV4Swap); }
-        if self.events.raydium_amm_v4_deposit { event_types.push(EventType::RaydiumAmmV4Deposit); }
-        if self.events.raydium_amm_v4_withdraw { event_types.push(EventType::RaydiumAmmV4Withdraw); }
-        if self.events.raydium_amm_v4_initialize2 { event_types.push(EventType::RaydiumAmmV4Initialize2); }
-        if self.events.raydium_amm_v4_withdraw_pnl { event_types.push(EventType::RaydiumAmmV4WithdrawPnl); }
-        if self.events.orca_whirlpool_swap { event_types.push(EventType::OrcaWhirlpoolSwap); }
-        if self.events.orca_whirlpool_liquidity_increased { event_types.push(EventType::OrcaWhirlpoolLiquidityIncreased); }
-        if self.events.orca_whirlpool_liquidity_decreased { event_types.push(EventType::OrcaWhirlpoolLiquidityDecreased); }
-        if self.events.orca_whirlpool_pool_initialized { event_types.push(EventType::OrcaWhirlpoolPoolInitialized); }
-        if self.events.meteora_pools_swap { event_types.push(EventType::MeteoraPoolsSwap); }
-        if self.events.meteora_pools_add_liquidity { event_types.push(EventType::MeteoraPoolsAddLiquidity); }
-        if self.events.meteora_pools_remove_liquidity { event_types.push(EventType::MeteoraPoolsRemoveLiquidity); }
-        if self.events.meteora_pools_bootstrap_liquidity { event_types.push(EventType::MeteoraPoolsBootstrapLiquidity); }
-        if self.events.meteora_pools_pool_created { event_types.push(EventType::MeteoraPoolsPoolCreated); }
-        if self.events.meteora_pools_set_pool_fees { event_types.push(EventType::MeteoraPoolsSetPoolFees); }
-        if self.events.meteora_damm_v2_swap { event_types.push(EventType::MeteoraDammV2Swap); }
-        if self.events.meteora_damm_v2_add_liquidity { event_types.push(EventType::MeteoraDammV2AddLiquidity); }
-        if self.events.meteora_damm_v2_remove_liquidity { event_types.push(EventType::MeteoraDammV2RemoveLiquidity); }
-        if self.events.meteora_damm_v2_initialize_pool { event_types.push(EventType::MeteoraDammV2InitializePool); }
-        if self.events.meteora_damm_v2_create_position { event_types.push(EventType::MeteoraDammV2CreatePosition); }
-        if self.events.meteora_damm_v2_close_position { event_types.push(EventType::MeteoraDammV2ClosePosition); }
-        if self.events.meteora_damm_v2_claim_position_fee { event_types.push(EventType::MeteoraDammV2ClaimPositionFee); }
-        if self.events.meteora_damm_v2_initialize_reward { event_types.push(EventType::MeteoraDammV2InitializeReward); }
-        if self.events.meteora_damm_v2_fund_reward { event_types.push(EventType::MeteoraDammV2FundReward); }
-        if self.events.meteora_damm_v2_claim_reward { event_types.push(EventType::MeteoraDammV2ClaimReward); }
-        if self.events.token_account { event_types.push(EventType::TokenAccount); }
-        if self.events.nonce_account { event_types.push(EventType::NonceAccount); }
-        if self.events.token_info { event_types.push(EventType::TokenInfo); }
-
-        event_types
-    }
-}
\ No newline at end of file
+    #[test]
+    fn test_event_flags() {
+        let config = events("[events]\nblock_meta = true\npumpfun_trade = true\npumpfun_create = false\n").unwrap();
+        assert_eq!(config.event_types().names(), vec!["BlockMeta", "PumpFunTrade"]);
+    }
+
+    #[test]
+    fn test_event_accessors() {
+        let mut config = events(r#"events = ["PumpFun*"]"#).unwrap();
+        config.set_enabled(EventType::PumpFunCreate, false);
+        config.set_enabled(EventType::BlockMeta, true);
+        assert!(config.is_enabled(EventType::PumpFunTrade));
+        assert!(!config.is_enabled(EventType::PumpFunCreate));
+        assert_eq!(config.event_types().names(), vec!["BlockMeta", "PumpFunTrade", "PumpFunComplete", "PumpFunMigrate"]);
+
+        let mut config = events("[events]\npumpfun_trade = true\n").unwrap();
+        config.set_enabled(EventType::PumpFunTrade, false);
+        config.set_enabled(EventType::RaydiumAmmV4Swap, true);
+        assert_eq!(config.event_types().names(), vec!["RaydiumAmmV4Swap"]);
+    }
+
+    fn write(content: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let config = Config::default();
+        config.save_to_file(file.path()).unwrap();
+        let loaded = Config::load_strict(file.path()).unwrap();
+        assert_eq!(loaded.events, config.events);
+        assert_eq!(loaded.get_enabled_event_types(), vec![EventType::PumpFunTrade, EventType::PumpFunCreate]);
+    }
+
+    #[test]
+    fn test_protocols_follow_events() {
+        let mut config = Config {
+            events: EventsConfig::Patterns(vec!["OrcaWhirlpoolSwap".to_string(), "Meteora*Swap".to_string()]),
+            ..Config::default()
+        };
+        config.protocols.bonk = Some(true);
+        assert_eq!(
+            config.get_enabled_protocol_names(),
+            vec!["pumpfun", "bonk", "orca_whirlpool", "meteora_pools", "meteora_damm_v2"]
+        );
+        assert_eq!(config.get_enabled_program_ids().len(), 5);
+        assert!(config
+            .get_enabled_program_ids()
+            .contains(&"whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string()));
+    }
+
+    #[test]
+    fn test_partial_file() {
+        let file = write("[server]\nport = 9100\n\n[grpc]\ntoken = \"secret\"\n\n[events]\npumpfun_trade = true\n");
+        let config = Config::load_strict(file.path()).unwrap();
+        assert_eq!(config.server.port, 9100);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.client_queue_capacity, 4096);
+        assert_eq!(config.grpc.endpoint, GrpcConfig::default().endpoint);
+        assert_eq!(config.protocols.pumpfun, None);
+        assert_eq!(config.get_enabled_protocol_names(), vec!["pumpfun"]);
+
+        let shipped = concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml");
+        Config::load_strict(shipped).unwrap();
+        Config::load_strict(format!("{}.example", shipped)).unwrap();
+    }
+
+    #[test]
+    fn test_strict_problems() {
+        let file = write(
+            r#"
+[server]
+host = "0.0.0.0"
+prot = 9100
+
+[grpc]
+endpoint = "http://localhost:9001"
+
+[protocols]
+pumpswap = false
+
+[events]
+pumpswap_buy = true
+pumpfun_trad = true
+"#,
+        );
+        let err = format!("{:#}", Config::load_strict(file.path()).unwrap_err());
+        assert!(err.contains("unknown key `server.prot`"), "{}", err);
+        assert!(err.contains("unknown key `events.pumpfun_trad`"), "{}", err);
+        assert!(err.contains("http://localhost:9001 is the proxy's own listener"), "{}", err);
+        assert!(err.contains("PumpSwapBuy is enabled but protocol `pumpswap` is switched off"), "{}", err);
+
+        // The lenient load keeps going with what it understood
+        let config = Config::load_or_default(file.path());
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert!(config.protocols.is_switched_off("pumpswap"));
+        assert!(!config.protocols.is_switched_off("bonk"));
+        assert!(Config::default().validate().is_ok());
+
+        // A config built in code gets the same checks, without a file
+        let mut config = Config::default();
+        config.protocols.pumpfun = Some(false);
+        config.grpc.endpoints = ["https://a.example.com", "http://127.0.0.1:9001", "https://a.example.com"]
+            .map(|endpoint| EndpointConfig {
+                endpoint: endpoint.to_string(),
+                token: None,
+                enable_tls: None,
+            })
+            .to_vec();
+        config.server.tls = Some(TlsConfig {
+            cert_path: "cert.pem".to_string(),
+            key_path: String::new(),
+            reload_interval_ms: 0,
+        });
+        let err = format!("{:#}", config.validate().unwrap_err());
+        assert!(err.contains("PumpFunTrade is enabled but protocol `pumpfun` is switched off"), "{}", err);
+        assert!(err.contains("http://127.0.0.1:9001 is the proxy's own listener"), "{}", err);
+        assert!(err.contains("https://a.example.com is listed more than once"), "{}", err);
+        assert!(err.contains("`server.tls.key_path` is empty"), "{}", err);
+        assert!(!err.contains("cert_path"), "{}", err);
+
+        // Switched off survives a save, left out stays left out
+        let saved = toml::to_string(&config.protocols).unwrap();
+        assert_eq!(saved.trim(), "pumpfun = false");
+        let loaded: ProtocolsConfig = toml::from_str(&saved).unwrap();
+        assert!(loaded.is_switched_off("pumpfun"));
+        assert_eq!(loaded.pumpswap, None);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut config = Config::default();
+        config
+            .apply_overrides([
+                ("server.port", "9100"),
+                ("grpc.token", "12345"),
+                ("events", "PumpFunTrade, RaydiumClmm*"),
+                ("grpc.endpoints.0.endpoint", "https://a.example.com"),
+                ("grpc.endpoints.0.token", "secret-a"),
+                ("server.auth.keys.0.key", "api-key"),
+            ])
+            .unwrap();
+        assert_eq!(config.server.port, 9100);
+        assert_eq!(config.grpc.token.as_deref(), Some("12345"));
+        assert_eq!(config.get_enabled_event_types().len(), 9);
+        assert_eq!(config.grpc.endpoints[0].endpoint, "https://a.example.com");
+
+        let printed = config.to_redacted_toml().unwrap();
+        assert!(!printed.contains("12345") && !printed.contains("secret-a") && !printed.contains("api-key"));
+        assert!(printed.contains("https://a.example.com"), "{}", printed);
+
+        let err = config.apply_overrides([("server.prot", "1")]).unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown key `server.prot`"), "{:#}", err);
+        assert!(config.apply_overrides([("server.port", "high")]).is_err());
+        assert_eq!(config.server.port, 9100);
+
+        config
+            .apply_env_overrides_from([("PPWS_SERVER__REPLAY_CAPACITY", "64"), ("HOME", "/root")])
+            .unwrap();
+        assert_eq!(config.server.replay_capacity, 64);
+
+        // Errors name where the bad value came from
+        let err = config
+            .apply_env_overrides_from([("PPWS_GRPC__TOKEN", "secret"), ("PPWS_SERVER__PORT", "high")])
+            .unwrap_err();
+        let err = format!("{:#}", err);
+        assert!(err.contains("`server.port` from PPWS_SERVER__PORT"), "{}", err);
+        assert!(!err.contains("PPWS_GRPC__TOKEN"), "{}", err);
+
+        let err = config
+            .apply_sourced_overrides([("--host", "server.host", "0.0.0.0"), ("--set", "server.prot", "1")])
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("`server.prot` from --set: unknown key"), "{:#}", err);
+        assert_eq!(config.server.port, 9100);
+    }
+}
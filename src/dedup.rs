@@ -0,0 +1,125 @@
+use crate::event_types::event_metadata;
+use sol_parser_sdk::DexEvent;
+use solana_sdk::signature::Signature;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// Identifies one event across feeds: the transaction and the event's
+/// position among the events parsed from it. sol-parser-sdk events carry no
+/// instruction index and a transaction can emit several events of one type,
+/// but every feed parses a transaction into the same events in the same
+/// order. The slot keeps signature-less events such as `BlockMeta` apart.
+type EventKey = (Signature, u64, u32);
+
+/// Numbers the events of each transaction in the order one feed delivers
+/// them; the SDK queues a transaction's events back to back
+#[derive(Default)]
+pub struct EmitOrder {
+    transaction: Option<(Signature, u64)>,
+    next: u32,
+}
+
+impl EmitOrder {
+    pub fn ordinal(&mut self, signature: Signature, slot: u64) -> u32 {
+        if self.transaction != Some((signature, slot)) {
+            self.transaction = Some((signature, slot));
+            self.next = 0;
+        }
+        let ordinal = self.next;
+        self.next += 1;
+        ordinal
+    }
+}
+
+/// Remembers the most recent `window` events so that, when several gRPC
+/// feeds deliver the same event, only the first copy is forwarded.
+///
+/// sol-parser-sdk exposes no instruction index, so an event is keyed by its
+/// signature, slot and the position at which its feed emitted it within the
+/// transaction (see `EmitOrder`). Copies are matched by that position, not
+/// by content: if two feeds emitted one transaction's events in a different
+/// order, the copy at each position from the slower feed would be dropped
+/// even when it is a different event, and clients would see one event twice
+/// and miss another. Feeds share the same filters and parser, so in practice
+/// they emit a transaction's events in the same order.
+pub struct Deduplicator {
+    seen: Mutex<Seen>,
+    window: usize,
+}
+
+struct Seen {
+    keys: HashSet<EventKey>,
+    order: VecDeque<EventKey>,
+}
+
+impl Deduplicator {
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            seen: Mutex::new(Seen {
+                keys: HashSet::with_capacity(window),
+                order: VecDeque::with_capacity(window),
+            }),
+            window,
+        }
+    }
+
+    /// Returns `true` the first time an event is offered; `order` numbers
+    /// the events of the feed it came from
+    pub fn first_seen(&self, event: &DexEvent, order: &mut EmitOrder) -> bool {
+        let Some(metadata) = event_metadata(event) else {
+            return true;
+        };
+        let ordinal = order.ordinal(metadata.signature, metadata.slot);
+        let key = (metadata.signature, metadata.slot, ordinal);
+
+        let mut seen = self.seen.lock().unwrap();
+        if !seen.keys.insert(key) {
+            return false;
+        }
+        seen.order.push_back(key);
+        if seen.order.len() > self.window {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::block_meta;
+
+    #[test]
+    fn test_first_copy_wins() {
+        let dedup = Deduplicator::new(2);
+        let (mut a, mut b) = (EmitOrder::default(), EmitOrder::default());
+        assert!(dedup.first_seen(&block_meta(1), &mut a));
+        assert!(!dedup.first_seen(&block_meta(1), &mut b));
+        assert!(dedup.first_seen(&block_meta(2), &mut a));
+
+        // Events without metadata are never held back
+        let error = DexEvent::Error("x".into());
+        assert!(dedup.first_seen(&error, &mut a));
+        assert!(dedup.first_seen(&error, &mut b));
+
+        // Slot 1 falls out of the window once a third key arrives
+        assert!(dedup.first_seen(&block_meta(3), &mut a));
+        assert!(dedup.first_seen(&block_meta(1), &mut a));
+    }
+
+    #[test]
+    fn test_repeated_events_in_one_transaction() {
+        let dedup = Deduplicator::new(16);
+        let (mut a, mut b) = (EmitOrder::default(), EmitOrder::default());
+
+        // Two events from the same transaction are both forwarded, and each
+        // is matched with its own copy from the other feed
+        assert!(dedup.first_seen(&block_meta(1), &mut a));
+        assert!(dedup.first_seen(&block_meta(1), &mut a));
+        assert!(!dedup.first_seen(&block_meta(1), &mut b));
+        assert!(!dedup.first_seen(&block_meta(1), &mut b));
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Proxy metadata wrapped around an event for clients that ask for it.
+///
+/// `seq` increases by one for every event the proxy takes off the parser
+/// queue, and every client receives events in `seq` order; a
+/// `NormalizedTrade` shares the `seq` of the swap it came from. Numbers a
+/// client's subscription or filter skip are not delivered, so the proxy
+/// reports loss explicitly instead: every matching event it drops or rate
+/// limits is announced by a `lagged` or `rate_limited` notice at the point
+/// in the stream where it was lost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<E> {
+    pub seq: u64,
+    /// Wire name of the event type, e.g. `PumpFunTrade` or `NormalizedTrade`
+    #[serde(rename = "type")]
+    pub event_type: Cow<'static, str>,
+    pub protocol: Cow<'static, str>,
+    /// When the proxy took the event off the parser queue (µs since epoch)
+    pub proxy_recv_us: i64,
+    /// When the proxy encoded the frame for sending (µs since epoch)
+    pub proxy_send_us: i64,
+    /// Labels attached by the server's event hooks
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub event: E,
+}
+
+/// The envelope fields known before the event is encoded
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub seq: u64,
+    pub event_type: &'static str,
+    pub protocol: &'static str,
+    pub proxy_recv_us: i64,
+}
+
+impl Header {
+    pub fn wrap<E>(&self, event: E, labels: &[String], proxy_send_us: i64) -> Envelope<E> {
+        Envelope {
+            seq: self.seq,
+            event_type: Cow::Borrowed(self.event_type),
+            protocol: Cow::Borrowed(self.protocol),
+            proxy_recv_us: self.proxy_recv_us,
+            proxy_send_us,
+            labels: labels.to_vec(),
+            event,
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use sol_parser_sdk::core::events::EventMetadata;
+use sol_parser_sdk::grpc::{program_ids, EventType};
+use sol_parser_sdk::DexEvent;
+
+/// Every `EventType` known to sol-parser-sdk together with its wire name.
+///
+/// The order matches the declaration order of `EventType`, so `t as usize`
+/// is normally the index of `t` in this table; `table_index` checks that
+/// rather than relying on it.
+pub const EVENT_TYPES: &[(&str, EventType)] = &[
+    ("BlockMeta", EventType::BlockMeta),
+    ("BonkTrade", EventType::BonkTrade),
+    ("BonkPoolCreate", EventType::BonkPoolCreate),
+    ("BonkMigrateAmm", EventType::BonkMigrateAmm),
+    ("PumpFunTrade", EventType::PumpFunTrade),
+    ("PumpFunCreate", EventType::PumpFunCreate),
+    ("PumpFunComplete", EventType::PumpFunComplete),
+    ("PumpFunMigrate", EventType::PumpFunMigrate),
+    ("PumpSwapBuy", EventType::PumpSwapBuy),
+    ("PumpSwapSell", EventType::PumpSwapSell),
+    ("PumpSwapCreatePool", EventType::PumpSwapCreatePool),
+    ("PumpSwapPoolCreated", EventType::PumpSwapPoolCreated),
+    ("PumpSwapTrade", EventType::PumpSwapTrade),
+    ("PumpSwapLiquidityAdded", EventType::PumpSwapLiquidityAdded),
+    ("PumpSwapLiquidityRemoved", EventType::PumpSwapLiquidityRemoved),
+    ("PumpSwapPoolUpdated", EventType::PumpSwapPoolUpdated),
+    ("PumpSwapFeesClaimed", EventType::PumpSwapFeesClaimed),
+    ("RaydiumCpmmSwap", EventType::RaydiumCpmmSwap),
+    ("RaydiumCpmmDeposit", EventType::RaydiumCpmmDeposit),
+    ("RaydiumCpmmWithdraw", EventType::RaydiumCpmmWithdraw),
+    ("RaydiumCpmmInitialize", EventType::RaydiumCpmmInitialize),
+    ("RaydiumClmmSwap", EventType::RaydiumClmmSwap),
+    ("RaydiumClmmCreatePool", EventType::RaydiumClmmCreatePool),
+    ("RaydiumClmmOpenPosition", EventType::RaydiumClmmOpenPosition),
+    ("RaydiumClmmClosePosition", EventType::RaydiumClmmClosePosition),
+    ("RaydiumClmmIncreaseLiquidity", EventType::RaydiumClmmIncreaseLiquidity),
+    ("RaydiumClmmDecreaseLiquidity", EventType::RaydiumClmmDecreaseLiquidity),
+    ("RaydiumClmmOpenPositionWithTokenExtNft", EventType::RaydiumClmmOpenPositionWithTokenExtNft),
+    ("RaydiumClmmCollectFee", EventType::RaydiumClmmCollectFee),
+    ("RaydiumAmmV4Swap", EventType::RaydiumAmmV4Swap),
+    ("RaydiumAmmV4Deposit", EventType::RaydiumAmmV4Deposit),
+    ("RaydiumAmmV4Withdraw", EventType::RaydiumAmmV4Withdraw),
+    ("RaydiumAmmV4Initialize2", EventType::RaydiumAmmV4Initialize2),
+    ("RaydiumAmmV4WithdrawPnl", EventType::RaydiumAmmV4WithdrawPnl),
+    ("OrcaWhirlpoolSwap", EventType::OrcaWhirlpoolSwap),
+    ("OrcaWhirlpoolLiquidityIncreased", EventType::OrcaWhirlpoolLiquidityIncreased),
+    ("OrcaWhirlpoolLiquidityDecreased", EventType::OrcaWhirlpoolLiquidityDecreased),
+    ("OrcaWhirlpoolPoolInitialized", EventType::OrcaWhirlpoolPoolInitialized),
+    ("MeteoraPoolsSwap", EventType::MeteoraPoolsSwap),
+    ("MeteoraPoolsAddLiquidity", EventType::MeteoraPoolsAddLiquidity),
+    ("MeteoraPoolsRemoveLiquidity", EventType::MeteoraPoolsRemoveLiquidity),
+    ("MeteoraPoolsBootstrapLiquidity", EventType::MeteoraPoolsBootstrapLiquidity),
+    ("MeteoraPoolsPoolCreated", EventType::MeteoraPoolsPoolCreated),
+    ("MeteoraPoolsSetPoolFees", EventType::MeteoraPoolsSetPoolFees),
+    ("MeteoraDammV2Swap", EventType::MeteoraDammV2Swap),
+    ("MeteoraDammV2AddLiquidity", EventType::MeteoraDammV2AddLiquidity),
+    ("MeteoraDammV2RemoveLiquidity", EventType::MeteoraDammV2RemoveLiquidity),
+    ("MeteoraDammV2InitializePool", EventType::MeteoraDammV2InitializePool),
+    ("MeteoraDammV2CreatePosition", EventType::MeteoraDammV2CreatePosition),
+    ("MeteoraDammV2ClosePosition", EventType::MeteoraDammV2ClosePosition),
+    ("MeteoraDammV2ClaimPositionFee", EventType::MeteoraDammV2ClaimPositionFee),
+    ("MeteoraDammV2InitializeReward", EventType::MeteoraDammV2InitializeReward),
+    ("MeteoraDammV2FundReward", EventType::MeteoraDammV2FundReward),
+    ("MeteoraDammV2ClaimReward", EventType::MeteoraDammV2ClaimReward),
+    ("TokenAccount", EventType::TokenAccount),
+    ("NonceAccount", EventType::NonceAccount),
+    ("TokenInfo", EventType::TokenInfo),
+];
+
+// `EventTypeSet` has one bit per entry
+const _: () = assert!(EVENT_TYPES.len() <= 64, "EventTypeSet needs a wider bit set");
+
+/// Position of `event_type` in `EVENT_TYPES`, `None` for a type the table
+/// is missing
+#[inline]
+pub(crate) fn table_index(event_type: EventType) -> Option<usize> {
+    let index = event_type as usize;
+    match EVENT_TYPES.get(index) {
+        Some((_, t)) if *t == event_type => Some(index),
+        _ => EVENT_TYPES.iter().position(|(_, t)| *t == event_type),
+    }
+}
+
+/// Wire name of an event type, identical to the `DexEvent` variant key;
+/// `Unknown` for a type `EVENT_TYPES` is missing
+pub fn event_type_name(event_type: EventType) -> &'static str {
+    table_index(event_type).map_or("Unknown", |index| EVENT_TYPES[index].0)
+}
+
+/// Look up an event type by its wire name (e.g. `"PumpFunTrade"`)
+pub fn parse_event_type(name: &str) -> Option<EventType> {
+    EVENT_TYPES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, t)| *t)
+}
+
+/// The `EventType` of a parsed event, or `None` for variants the gRPC
+/// filter has no type for (Meteora DLMM and `Error`)
+pub fn event_type_of(event: &DexEvent) -> Option<EventType> {
+    let event_type = match event {
+        DexEvent::BlockMeta(_) => EventType::BlockMeta,
+        DexEvent::BonkTrade(_) => EventType::BonkTrade,
+        DexEvent::BonkPoolCreate(_) => EventType::BonkPoolCreate,
+        DexEvent::BonkMigrateAmm(_) => EventType::BonkMigrateAmm,
+        DexEvent::PumpFunTrade(_) => EventType::PumpFunTrade,
+        DexEvent::PumpFunCreate(_) => EventType::PumpFunCreate,
+        DexEvent::PumpFunComplete(_) => EventType::PumpFunComplete,
+        DexEvent::PumpFunMigrate(_) => EventType::PumpFunMigrate,
+        DexEvent::PumpSwapBuy(_) => EventType::PumpSwapBuy,
+        DexEvent::PumpSwapSell(_) => EventType::PumpSwapSell,
+        DexEvent::PumpSwapCreatePool(_) => EventType::PumpSwapCreatePool,
+        DexEvent::PumpSwapPoolCreated(_) => EventType::PumpSwapPoolCreated,
+        DexEvent::PumpSwapTrade(_) => EventType::PumpSwapTrade,
+        DexEvent::PumpSwapLiquidityAdded(_) => EventType::PumpSwapLiquidityAdded,
+        DexEvent::PumpSwapLiquidityRemoved(_) => EventType::PumpSwapLiquidityRemoved,
+        DexEvent::PumpSwapPoolUpdated(_) => EventType::PumpSwapPoolUpdated,
+        DexEvent::PumpSwapFeesClaimed(_) => EventType::PumpSwapFeesClaimed,
+        DexEvent::RaydiumCpmmSwap(_) => EventType::RaydiumCpmmSwap,
+        DexEvent::RaydiumCpmmDeposit(_) => EventType::RaydiumCpmmDeposit,
+        DexEvent::RaydiumCpmmWithdraw(_) => EventType::RaydiumCpmmWithdraw,
+        DexEvent::RaydiumCpmmInitialize(_) => EventType::RaydiumCpmmInitialize,
+        DexEvent::RaydiumClmmSwap(_) => EventType::RaydiumClmmSwap,
+        DexEvent::RaydiumClmmCreatePool(_) => EventType::RaydiumClmmCreatePool,
+        DexEvent::RaydiumClmmOpenPosition(_) => EventType::RaydiumClmmOpenPosition,
+        DexEvent::RaydiumClmmClosePosition(_) => EventType::RaydiumClmmClosePosition,
+        DexEvent::RaydiumClmmIncreaseLiquidity(_) => EventType::RaydiumClmmIncreaseLiquidity,
+        DexEvent::RaydiumClmmDecreaseLiquidity(_) => EventType::RaydiumClmmDecreaseLiquidity,
+        DexEvent::RaydiumClmmOpenPositionWithTokenExtNft(_) => {
+            EventType::RaydiumClmmOpenPositionWithTokenExtNft
+        }
+        DexEvent::RaydiumClmmCollectFee(_) => EventType::RaydiumClmmCollectFee,
+        DexEvent::RaydiumAmmV4Swap(_) => EventType::RaydiumAmmV4Swap,
+        DexEvent::RaydiumAmmV4Deposit(_) => EventType::RaydiumAmmV4Deposit,
+        DexEvent::RaydiumAmmV4Withdraw(_) => EventType::RaydiumAmmV4Withdraw,
+        DexEvent::RaydiumAmmV4Initialize2(_) => EventType::RaydiumAmmV4Initialize2,
+        DexEvent::RaydiumAmmV4WithdrawPnl(_) => EventType::RaydiumAmmV4WithdrawPnl,
+        DexEvent::OrcaWhirlpoolSwap(_) => EventType::OrcaWhirlpoolSwap,
+        DexEvent::OrcaWhirlpoolLiquidityIncreased(_) => EventType::OrcaWhirlpoolLiquidityIncreased,
+        DexEvent::OrcaWhirlpoolLiquidityDecreased(_) => EventType::OrcaWhirlpoolLiquidityDecreased,
+        DexEvent::OrcaWhirlpoolPoolInitialized(_) => EventType::OrcaWhirlpoolPoolInitialized,
+        DexEvent::MeteoraPoolsSwap(_) => EventType::MeteoraPoolsSwap,
+        DexEvent::MeteoraPoolsAddLiquidity(_) => EventType::MeteoraPoolsAddLiquidity,
+        DexEvent::MeteoraPoolsRemoveLiquidity(_) => EventType::MeteoraPoolsRemoveLiquidity,
+        DexEvent::MeteoraPoolsBootstrapLiquidity(_) => EventType::MeteoraPoolsBootstrapLiquidity,
+        DexEvent::MeteoraPoolsPoolCreated(_) => EventType::MeteoraPoolsPoolCreated,
+        DexEvent::MeteoraPoolsSetPoolFees(_) => EventType::MeteoraPoolsSetPoolFees,
+        DexEvent::MeteoraDammV2Swap(_) => EventType::MeteoraDammV2Swap,
+        DexEvent::MeteoraDammV2AddLiquidity(_) => EventType::MeteoraDammV2AddLiquidity,
+        DexEvent::MeteoraDammV2RemoveLiquidity(_) => EventType::MeteoraDammV2RemoveLiquidity,
+        DexEvent::MeteoraDammV2InitializePool(_) => EventType::MeteoraDammV2InitializePool,
+        DexEvent::MeteoraDammV2CreatePosition(_) => EventType::MeteoraDammV2CreatePosition,
+        DexEvent::MeteoraDammV2ClosePosition(_) => EventType::MeteoraDammV2ClosePosition,
+        DexEvent::MeteoraDammV2ClaimPositionFee(_) => EventType::MeteoraDammV2ClaimPositionFee,
+        DexEvent::MeteoraDammV2InitializeReward(_) => EventType::MeteoraDammV2InitializeReward,
+        DexEvent::MeteoraDammV2FundReward(_) => EventType::MeteoraDammV2FundReward,
+        DexEvent::MeteoraDammV2ClaimReward(_) => EventType::MeteoraDammV2ClaimReward,
+        DexEvent::TokenAccount(_) => EventType::TokenAccount,
+        DexEvent::NonceAccount(_) => EventType::NonceAccount,
+        DexEvent::TokenInfo(_) => EventType::TokenInfo,
+        _ => return None,
+    };
+    Some(event_type)
+}
+
+/// Protocol name prefixes of `EVENT_TYPES` wire names, with the protocol
+/// name used in envelopes (matching the `[protocols]` config keys) and the
+/// program the gRPC subscription watches for it. sol-parser-sdk's `Protocol`
+/// has no Orca or Meteora variants, so their program ids are listed here.
+const PROTOCOLS: &[(&str, &str, &str)] = &[
+    ("PumpFun", "pumpfun", program_ids::PUMPFUN_PROGRAM_ID),
+    ("PumpSwap", "pumpswap", program_ids::PUMPSWAP_PROGRAM_ID),
+    ("Bonk", "bonk", program_ids::BONK_PROGRAM_ID),
+    ("RaydiumCpmm", "raydium_cpmm", program_ids::RAYDIUM_CPMM_PROGRAM_ID),
+    ("RaydiumClmm", "raydium_clmm", program_ids::RAYDIUM_CLMM_PROGRAM_ID),
+    ("RaydiumAmmV4", "raydium_amm_v4", program_ids::RAYDIUM_AMM_V4_PROGRAM_ID),
+    ("OrcaWhirlpool", "orca_whirlpool", "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"),
+    ("MeteoraPools", "meteora_pools", "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB"),
+    ("MeteoraDammV2", "meteora_damm_v2", "cpamdpZCGKUy5JxQXB4dcpGPiikHswMGs6PShBcb5Wy"),
+];
+
+/// The protocol an event type belongs to; chain-level events such as
+/// `BlockMeta` and account updates report `solana`
+pub fn protocol_name(event_type: EventType) -> &'static str {
+    let name = event_type_name(event_type);
+    PROTOCOLS
+        .iter()
+        .find(|(prefix, _, _)| name.starts_with(prefix))
+        .map(|(_, protocol, _)| *protocol)
+        .unwrap_or("solana")
+}
+
+/// Every `[protocols]` config key, in table order
+pub fn protocol_names() -> impl Iterator<Item = &'static str> {
+    PROTOCOLS.iter().map(|(_, protocol, _)| *protocol)
+}
+
+/// The program a protocol's events come from, `None` for `solana` and
+/// unknown names
+pub fn protocol_program_id(protocol: &str) -> Option<&'static str> {
+    PROTOCOLS
+        .iter()
+        .find(|(_, name, _)| *name == protocol)
+        .map(|(_, _, program_id)| *program_id)
+}
+
+/// The `[events]` config key of an event type, e.g. `pumpfun_trade` or
+/// `raydium_amm_v4_initialize2`: the protocol key followed by the rest of the
+/// wire name in snake case
+pub fn event_config_key(event_type: EventType) -> String {
+    let name = event_type_name(event_type);
+    let (mut key, rest) = match PROTOCOLS.iter().find(|(prefix, _, _)| name.starts_with(prefix)) {
+        Some((prefix, protocol, _)) => (protocol.to_string(), &name[prefix.len()..]),
+        None => (String::new(), name),
+    };
+    for c in rest.chars() {
+        if c.is_ascii_uppercase() {
+            if !key.is_empty() {
+                key.push('_');
+            }
+            key.push(c.to_ascii_lowercase());
+        } else {
+            key.push(c);
+        }
+    }
+    key
+}
+
+/// Event types whose wire name matches `pattern`, where `*` matches any
+/// run of characters, e.g. `RaydiumClmm*` or `Meteora*Swap`
+pub fn match_event_types(pattern: &str) -> EventTypeSet {
+    EVENT_TYPES
+        .iter()
+        .filter(|(name, _)| glob_match(pattern.as_bytes(), name.as_bytes()))
+        .map(|(_, t)| *t)
+        .collect()
+}
+
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| glob_match(rest, &name[i..])),
+        Some((c, rest)) => name.first() == Some(c) && glob_match(rest, &name[1..]),
+    }
+}
+
+/// The metadata of a parsed event, or `None` for `Error`
+pub fn event_metadata(event: &DexEvent) -> Option<&EventMetadata> {
+    macro_rules! metadata {
+        ($($variant:ident),* $(,)?) => {
+            match event {
+                $(DexEvent::$variant(e) => Some(&e.metadata),)*
+                _ => None,
+            }
+        };
+    }
+
+    metadata!(
+        PumpFunCreate, PumpFunTrade, PumpFunComplete, PumpFunMigrate,
+        BonkTrade, BonkPoolCreate, BonkMigrateAmm, PumpSwapBuy,
+        PumpSwapSell, PumpSwapCreatePool, PumpSwapPoolCreated, PumpSwapTrade,
+        PumpSwapLiquidityAdded, PumpSwapLiquidityRemoved, PumpSwapPoolUpdated, PumpSwapFeesClaimed,
+        RaydiumClmmSwap, RaydiumClmmCreatePool, RaydiumClmmOpenPosition, RaydiumClmmOpenPositionWithTokenExtNft,
+        RaydiumClmmClosePosition, RaydiumClmmIncreaseLiquidity, RaydiumClmmDecreaseLiquidity, RaydiumClmmCollectFee,
+        RaydiumCpmmSwap, RaydiumCpmmDeposit, RaydiumCpmmWithdraw, RaydiumCpmmInitialize,
+        RaydiumAmmV4Swap, RaydiumAmmV4Deposit, RaydiumAmmV4Initialize2, RaydiumAmmV4Withdraw,
+        RaydiumAmmV4WithdrawPnl, OrcaWhirlpoolSwap, OrcaWhirlpoolLiquidityIncreased, OrcaWhirlpoolLiquidityDecreased,
+        OrcaWhirlpoolPoolInitialized, MeteoraPoolsSwap, MeteoraPoolsAddLiquidity, MeteoraPoolsRemoveLiquidity,
+        MeteoraPoolsBootstrapLiquidity, MeteoraPoolsPoolCreated, MeteoraPoolsSetPoolFees, MeteoraDammV2Swap,
+        MeteoraDammV2AddLiquidity, MeteoraDammV2RemoveLiquidity, MeteoraDammV2InitializePool, MeteoraDammV2CreatePosition,
+        MeteoraDammV2ClosePosition, MeteoraDammV2ClaimPositionFee, MeteoraDammV2InitializeReward, MeteoraDammV2FundReward,
+        MeteoraDammV2ClaimReward, MeteoraDlmmSwap, MeteoraDlmmAddLiquidity, MeteoraDlmmRemoveLiquidity,
+        MeteoraDlmmInitializePool, MeteoraDlmmInitializeBinArray, MeteoraDlmmCreatePosition, MeteoraDlmmClosePosition,
+        MeteoraDlmmClaimFee, TokenAccount, NonceAccount, BlockMeta,
+        TokenInfo,
+    )
+}
+
+/// A compact set of event types, one bit per `EVENT_TYPES` entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventTypeSet(u64);
+
+impl EventTypeSet {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn all() -> Self {
+        Self(u64::MAX >> (64 - EVENT_TYPES.len()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    #[inline]
+    fn bit(event_type: EventType) -> u64 {
+        table_index(event_type).map_or(0, |index| 1 << index)
+    }
+
+    /// Types missing from `EVENT_TYPES` can't be added
+    pub fn insert(&mut self, event_type: EventType) {
+        self.0 |= Self::bit(event_type);
+    }
+
+    pub fn remove(&mut self, event_type: EventType) {
+        self.0 &= !Self::bit(event_type);
+    }
+
+    #[inline]
+    pub fn contains(&self, event_type: EventType) -> bool {
+        self.0 & Self::bit(event_type) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = EventType> + '_ {
+        EVENT_TYPES
+            .iter()
+            .map(|(_, t)| *t)
+            .filter(move |t| self.contains(*t))
+    }
+
+    /// Wire names of the contained event types, in `EVENT_TYPES` order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.iter().map(event_type_name).collect()
+    }
+}
+
+impl FromIterator<EventType> for EventTypeSet {
+    fn from_iter<I: IntoIterator<Item = EventType>>(iter: I) -> Self {
+        let mut set = Self::empty();
+        for event_type in iter {
+            set.insert(event_type);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The wire name of every SDK `EventType` variant. The match has no
+    /// wildcard, so a variant added to the SDK stops this from compiling.
+    fn sdk_name(event_type: EventType) -> &'static str {
+        match event_type {
+            EventType::BlockMeta => "BlockMeta",
+            EventType::BonkTrade => "BonkTrade",
+            EventType::BonkPoolCreate => "BonkPoolCreate",
+            EventType::BonkMigrateAmm => "BonkMigrateAmm",
+            EventType::PumpFunTrade => "PumpFunTrade",
+            EventType::PumpFunCreate => "PumpFunCreate",
+            EventType::PumpFunComplete => "PumpFunComplete",
+            EventType::PumpFunMigrate => "PumpFunMigrate",
+            EventType::PumpSwapBuy => "PumpSwapBuy",
+            EventType::PumpSwapSell => "PumpSwapSell",
+            EventType::PumpSwapCreatePool => "PumpSwapCreatePool",
+            EventType::PumpSwapPoolCreated => "PumpSwapPoolCreated",
+            EventType::PumpSwapTrade => "PumpSwapTrade",
+            EventType::PumpSwapLiquidityAdded => "PumpSwapLiquidityAdded",
+            EventType::PumpSwapLiquidityRemoved => "PumpSwapLiquidityRemoved",
+            EventType::PumpSwapPoolUpdated => "PumpSwapPoolUpdated",
+            EventType::PumpSwapFeesClaimed => "PumpSwapFeesClaimed",
+            EventType::RaydiumCpmmSwap => "RaydiumCpmmSwap",
+            EventType::RaydiumCpmmDeposit => "RaydiumCpmmDeposit",
+            EventType::RaydiumCpmmWithdraw => "RaydiumCpmmWithdraw",
+            EventType::RaydiumCpmmInitialize => "RaydiumCpmmInitialize",
+            EventType::RaydiumClmmSwap => "RaydiumClmmSwap",
+            EventType::RaydiumClmmCreatePool => "RaydiumClmmCreatePool",
+            EventType::RaydiumClmmOpenPosition => "RaydiumClmmOpenPosition",
+            EventType::RaydiumClmmClosePosition => "RaydiumClmmClosePosition",
+            EventType::RaydiumClmmIncreaseLiquidity => "RaydiumClmmIncreaseLiquidity",
+            EventType::RaydiumClmmDecreaseLiquidity => "RaydiumClmmDecreaseLiquidity",
+            EventType::RaydiumClmmOpenPositionWithTokenExtNft => "RaydiumClmmOpenPositionWithTokenExtNft",
+            EventType::RaydiumClmmCollectFee => "RaydiumClmmCollectFee",
+            EventType::RaydiumAmmV4Swap => "RaydiumAmmV4Swap",
+            EventType::RaydiumAmmV4Deposit => "RaydiumAmmV4Deposit",
+            EventType::RaydiumAmmV4Withdraw => "RaydiumAmmV4Withdraw",
+            EventType::RaydiumAmmV4Initialize2 => "RaydiumAmmV4Initialize2",
+            EventType::RaydiumAmmV4WithdrawPnl => "RaydiumAmmV4WithdrawPnl",
+            EventType::OrcaWhirlpoolSwap => "OrcaWhirlpoolSwap",
+            EventType::OrcaWhirlpoolLiquidityIncreased => "OrcaWhirlpoolLiquidityIncreased",
+            EventType::OrcaWhirlpoolLiquidityDecreased => "OrcaWhirlpoolLiquidityDecreased",
+            EventType::OrcaWhirlpoolPoolInitialized => "OrcaWhirlpoolPoolInitialized",
+            EventType::MeteoraPoolsSwap => "MeteoraPoolsSwap",
+            EventType::MeteoraPoolsAddLiquidity => "MeteoraPoolsAddLiquidity",
+            EventType::MeteoraPoolsRemoveLiquidity => "MeteoraPoolsRemoveLiquidity",
+            EventType::MeteoraPoolsBootstrapLiquidity => "MeteoraPoolsBootstrapLiquidity",
+            EventType::MeteoraPoolsPoolCreated => "MeteoraPoolsPoolCreated",
+            EventType::MeteoraPoolsSetPoolFees => "MeteoraPoolsSetPoolFees",
+            EventType::MeteoraDammV2Swap => "MeteoraDammV2Swap",
+            EventType::MeteoraDammV2AddLiquidity => "MeteoraDammV2AddLiquidity",
+            EventType::MeteoraDammV2RemoveLiquidity => "MeteoraDammV2RemoveLiquidity",
+            EventType::MeteoraDammV2InitializePool => "MeteoraDammV2InitializePool",
+            EventType::MeteoraDammV2CreatePosition => "MeteoraDammV2CreatePosition",
+            EventType::MeteoraDammV2ClosePosition => "MeteoraDammV2ClosePosition",
+            EventType::MeteoraDammV2ClaimPositionFee => "MeteoraDammV2ClaimPositionFee",
+            EventType::MeteoraDammV2InitializeReward => "MeteoraDammV2InitializeReward",
+            EventType::MeteoraDammV2FundReward => "MeteoraDammV2FundReward",
+            EventType::MeteoraDammV2ClaimReward => "MeteoraDammV2ClaimReward",
+            EventType::TokenAccount => "TokenAccount",
+            EventType::NonceAccount => "NonceAccount",
+            EventType::TokenInfo => "TokenInfo",
+        }
+    }
+
+    #[test]
+    fn test_table_matches_sdk_enum() {
+        for (i, (name, event_type)) in EVENT_TYPES.iter().enumerate() {
+            assert_eq!(sdk_name(*event_type), *name);
+            assert_eq!(table_index(*event_type), Some(i), "{} is listed twice", name);
+            assert_eq!(parse_event_type(name), Some(*event_type));
+            assert_eq!(event_type_name(*event_type), *name);
+        }
+        // `sdk_name` has an arm for each of the SDK's 57 variants, and the
+        // entries are distinct, so none is missing
+        assert_eq!(EVENT_TYPES.len(), 57);
+    }
+
+    #[test]
+    fn test_protocol_name() {
+        assert_eq!(protocol_name(EventType::PumpSwapBuy), "pumpswap");
+        assert_eq!(protocol_name(EventType::RaydiumAmmV4Swap), "raydium_amm_v4");
+        assert_eq!(protocol_name(EventType::BlockMeta), "solana");
+    }
+
+    #[test]
+    fn test_event_config_key() {
+        assert_eq!(event_config_key(EventType::BlockMeta), "block_meta");
+        assert_eq!(event_config_key(EventType::PumpFunTrade), "pumpfun_trade");
+        assert_eq!(event_config_key(EventType::RaydiumAmmV4Initialize2), "raydium_amm_v4_initialize2");
+        assert_eq!(
+            event_config_key(EventType::RaydiumClmmOpenPositionWithTokenExtNft),
+            "raydium_clmm_open_position_with_token_ext_nft"
+        );
+        assert_eq!(event_config_key(EventType::MeteoraDammV2ClaimReward), "meteora_damm_v2_claim_reward");
+    }
+
+    #[test]
+    fn test_match_event_types() {
+        assert_eq!(match_event_types("PumpFunTrade").names(), vec!["PumpFunTrade"]);
+        assert_eq!(
+            match_event_types("Meteora*Swap").names(),
+            vec!["MeteoraPoolsSwap", "MeteoraDammV2Swap"]
+        );
+        assert_eq!(match_event_types("RaydiumClmm*").iter().count(), 8);
+        assert_eq!(match_event_types("*").iter().count(), EVENT_TYPES.len());
+        assert!(match_event_types("PumpFun").is_empty());
+        assert!(match_event_types("pumpfun*").is_empty());
+    }
+
+    #[test]
+    fn test_event_type_set() {
+        let mut set = EventTypeSet::empty();
+        set.insert(EventType::PumpFunTrade);
+        set.insert(EventType::TokenInfo);
+        assert!(set.contains(EventType::PumpFunTrade));
+        assert!(!set.contains(EventType::PumpFunCreate));
+        assert_eq!(set.names(), vec!["PumpFunTrade", "TokenInfo"]);
+
+        set.remove(EventType::PumpFunTrade);
+        assert_eq!(set.names(), vec!["TokenInfo"]);
+        assert_eq!(EventTypeSet::all().iter().count(), EVENT_TYPES.len());
+    }
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use sol_parser_sdk::DexEvent;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Accounts of an event that a client can filter on.
+///
+/// Fields the event does not carry stay `None`; a few events reference two
+/// mints (token pairs) or two pools (migrations).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventKeys {
+    pub mints: [Option<Pubkey>; 2],
+    pub pools: [Option<Pubkey>; 2],
+    pub wallet: Option<Pubkey>,
+    pub creator: Option<Pubkey>,
+}
+
+impl EventKeys {
+    fn mint(mint: Pubkey) -> Self {
+        Self { mints: [Some(mint), None], ..Self::default() }
+    }
+
+    fn pool(pool: Pubkey) -> Self {
+        Self { pools: [Some(pool), None], ..Self::default() }
+    }
+
+    fn with_mint(mut self, mint: Pubkey) -> Self {
+        if self.mints[0].is_none() {
+            self.mints[0] = Some(mint);
+        } else {
+            self.mints[1] = Some(mint);
+        }
+        self
+    }
+
+    fn with_pool(mut self, pool: Pubkey) -> Self {
+        if self.pools[0].is_none() {
+            self.pools[0] = Some(pool);
+        } else {
+            self.pools[1] = Some(pool);
+        }
+        self
+    }
+
+    fn with_wallet(mut self, wallet: Pubkey) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    fn with_creator(mut self, creator: Pubkey) -> Self {
+        self.creator = Some(creator);
+        self
+    }
+}
+
+/// Extract the filterable accounts of a parsed event
+pub fn event_keys(event: &DexEvent) -> EventKeys {
+    match event {
+        DexEvent::PumpFunTrade(e) => EventKeys::mint(e.mint).with_wallet(e.user).with_creator(e.creator),
+        DexEvent::PumpFunCreate(e) => EventKeys::mint(e.mint)
+            .with_pool(e.bonding_curve)
+            .with_wallet(e.user)
+            .with_creator(e.creator),
+        DexEvent::PumpFunComplete(e) => EventKeys::mint(e.mint).with_pool(e.bonding_curve).with_wallet(e.user),
+        DexEvent::PumpFunMigrate(e) => EventKeys::mint(e.mint)
+            .with_pool(e.bonding_curve)
+            .with_pool(e.pool)
+            .with_wallet(e.user),
+
+        DexEvent::BonkTrade(e) => EventKeys::pool(e.pool_state).with_wallet(e.user),
+        DexEvent::BonkPoolCreate(e) => EventKeys::pool(e.pool_state).with_creator(e.creator),
+        DexEvent::BonkMigrateAmm(e) => EventKeys::pool(e.old_pool).with_pool(e.new_pool).with_wallet(e.user),
+
+        DexEvent::PumpSwapBuy(e) => EventKeys::mint(e.token_mint).with_pool(e.pool_id).with_wallet(e.user),
+        DexEvent::PumpSwapSell(e) => EventKeys::mint(e.token_mint).with_pool(e.pool_id).with_wallet(e.user),
+        DexEvent::PumpSwapCreatePool(e) => EventKeys::mint(e.token_mint).with_pool(e.pool_id).with_creator(e.creator),
+        DexEvent::PumpSwapPoolCreated(e) => EventKeys::mint(e.token_a_mint)
+            .with_mint(e.token_b_mint)
+            .with_pool(e.pool_account)
+            .with_creator(e.creator),
+        DexEvent::PumpSwapTrade(e) => EventKeys::mint(e.token_in_mint)
+            .with_mint(e.token_out_mint)
+            .with_pool(e.pool_account)
+            .with_wallet(e.user),
+        DexEvent::PumpSwapLiquidityAdded(e) => EventKeys::mint(e.token_a_mint)
+            .with_mint(e.token_b_mint)
+            .with_pool(e.pool_account)
+            .with_wallet(e.user),
+        DexEvent::PumpSwapLiquidityRemoved(e) => EventKeys::mint(e.token_a_mint)
+            .with_mint(e.token_b_mint)
+            .with_pool(e.pool_account)
+            .with_wallet(e.user),
+        DexEvent::PumpSwapPoolUpdated(e) => EventKeys::pool(e.pool_account),
+        DexEvent::PumpSwapFeesClaimed(e) => EventKeys::pool(e.pool_account),
+
+        DexEvent::RaydiumCpmmSwap(e) => EventKeys::pool(e.pool_id),
+        DexEvent::RaydiumCpmmDeposit(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumCpmmWithdraw(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumCpmmInitialize(e) => EventKeys::pool(e.pool).with_creator(e.creator),
+
+        DexEvent::RaydiumClmmSwap(e) => EventKeys::pool(e.pool_state).with_wallet(e.sender),
+        DexEvent::RaydiumClmmCreatePool(e) => EventKeys::pool(e.pool).with_creator(e.creator),
+        DexEvent::RaydiumClmmOpenPosition(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumClmmOpenPositionWithTokenExtNft(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumClmmClosePosition(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumClmmIncreaseLiquidity(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumClmmDecreaseLiquidity(e) => EventKeys::pool(e.pool).with_wallet(e.user),
+        DexEvent::RaydiumClmmCollectFee(e) => EventKeys::pool(e.pool_state),
+
+        DexEvent::RaydiumAmmV4Swap(e) => EventKeys::pool(e.amm).with_wallet(e.user_source_owner),
+        DexEvent::RaydiumAmmV4Deposit(e) => EventKeys::pool(e.amm).with_wallet(e.user_owner),
+        DexEvent::RaydiumAmmV4Withdraw(e) => EventKeys::pool(e.amm).with_wallet(e.user_owner),
+        DexEvent::RaydiumAmmV4Initialize2(e) => EventKeys::mint(e.coin_mint)
+            .with_mint(e.pc_mint)
+            .with_pool(e.amm)
+            .with_creator(e.user_wallet),
+        DexEvent::RaydiumAmmV4WithdrawPnl(e) => EventKeys::pool(e.amm).with_wallet(e.pnl_owner),
+
+        DexEvent::OrcaWhirlpoolSwap(e) => EventKeys::pool(e.whirlpool),
+        DexEvent::OrcaWhirlpoolLiquidityIncreased(e) => EventKeys::pool(e.whirlpool),
+        DexEvent::OrcaWhirlpoolLiquidityDecreased(e) => EventKeys::pool(e.whirlpool),
+        DexEvent::OrcaWhirlpoolPoolInitialized(e) => EventKeys::mint(e.token_mint_a)
+            .with_mint(e.token_mint_b)
+            .with_pool(e.whirlpool),
+
+        DexEvent::MeteoraPoolsBootstrapLiquidity(e) => EventKeys::pool(e.pool),
+        DexEvent::MeteoraPoolsPoolCreated(e) => EventKeys::mint(e.token_a_mint)
+            .with_mint(e.token_b_mint)
+            .with_pool(e.pool),
+        DexEvent::MeteoraPoolsSetPoolFees(e) => EventKeys::pool(e.pool),
+
+        DexEvent::MeteoraDammV2Swap(e) => EventKeys::pool(e.lb_pair).with_wallet(e.from),
+        DexEvent::MeteoraDammV2AddLiquidity(e) => EventKeys::pool(e.lb_pair).with_wallet(e.from),
+        DexEvent::MeteoraDammV2RemoveLiquidity(e) => EventKeys::pool(e.lb_pair).with_wallet(e.from),
+        DexEvent::MeteoraDammV2InitializePool(e) => EventKeys::mint(e.token_x)
+            .with_mint(e.token_y)
+            .with_pool(e.lb_pair),
+        DexEvent::MeteoraDammV2CreatePosition(e) => EventKeys::pool(e.lb_pair).with_wallet(e.owner),
+        DexEvent::MeteoraDammV2ClosePosition(e) => EventKeys::default().with_wallet(e.owner),
+        DexEvent::MeteoraDammV2ClaimPositionFee(e) => EventKeys::pool(e.lb_pair).with_wallet(e.owner),
+        DexEvent::MeteoraDammV2InitializeReward(e) => EventKeys::pool(e.lb_pair).with_wallet(e.funder),
+        DexEvent::MeteoraDammV2FundReward(e) => EventKeys::pool(e.lb_pair).with_wallet(e.funder),
+        DexEvent::MeteoraDammV2ClaimReward(e) => EventKeys::pool(e.lb_pair).with_wallet(e.owner),
+
+        DexEvent::TokenAccount(e) => EventKeys::mint(e.mint).with_wallet(e.owner),
+        DexEvent::NonceAccount(e) => EventKeys::default().with_wallet(e.authority),
+        DexEvent::TokenInfo(e) => EventKeys::mint(e.mint),
+
+        _ => EventKeys::default(),
+    }
+}
+
+/// Account filters requested by a client, as sent over the wire
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilterSpec {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wallets: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub creators: Vec<String>,
+}
+
+/// Per-client account filter.
+///
+/// Each non-empty list must match at least one account of the event; empty
+/// lists don't constrain anything. An empty filter passes every event.
+#[derive(Debug, Clone, Default)]
+pub struct ClientFilter {
+    mints: HashSet<Pubkey>,
+    pools: HashSet<Pubkey>,
+    wallets: HashSet<Pubkey>,
+    creators: HashSet<Pubkey>,
+}
+
+impl ClientFilter {
+    pub fn from_spec(spec: &FilterSpec) -> Result<Self, String> {
+        Ok(Self {
+            mints: parse_pubkeys(&spec.mints)?,
+            pools: parse_pubkeys(&spec.pools)?,
+            wallets: parse_pubkeys(&spec.wallets)?,
+            creators: parse_pubkeys(&spec.creators)?,
+        })
+    }
+
+    pub fn to_spec(&self) -> FilterSpec {
+        FilterSpec {
+            mints: self.mints.iter().map(Pubkey::to_string).collect(),
+            pools: self.pools.iter().map(Pubkey::to_string).collect(),
+            wallets: self.wallets.iter().map(Pubkey::to_string).collect(),
+            creators: self.creators.iter().map(Pubkey::to_string).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mints.is_empty()
+            && self.pools.is_empty()
+            && self.wallets.is_empty()
+            && self.creators.is_empty()
+    }
+
+    pub fn matches(&self, keys: &EventKeys) -> bool {
+        matches_any(&self.mints, &keys.mints)
+            && matches_any(&self.pools, &keys.pools)
+            && matches_any(&self.wallets, &[keys.wallet])
+            && matches_any(&self.creators, &[keys.creator])
+    }
+}
+
+#[inline]
+fn matches_any(wanted: &HashSet<Pubkey>, keys: &[Option<Pubkey>]) -> bool {
+    wanted.is_empty() || keys.iter().flatten().any(|key| wanted.contains(key))
+}
+
+fn parse_pubkeys(values: &[String]) -> Result<HashSet<Pubkey>, String> {
+    values
+        .iter()
+        .map(|value| Pubkey::from_str(value).map_err(|_| format!("invalid pubkey: {}", value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_filter_matching() {
+        let mint = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let keys = EventKeys::mint(mint).with_wallet(wallet);
+
+        assert!(ClientFilter::default().matches(&keys));
+
+        let spec = FilterSpec { mints: vec![mint.to_string()], ..FilterSpec::default() };
+        let filter = ClientFilter::from_spec(&spec).unwrap();
+        assert!(filter.matches(&keys));
+        assert!(!filter.matches(&EventKeys::mint(Pubkey::new_unique())));
+
+        // Categories are combined with AND
+        let spec = FilterSpec {
+            mints: vec![mint.to_string()],
+            wallets: vec![Pubkey::new_unique().to_string()],
+            ..FilterSpec::default()
+        };
+        assert!(!ClientFilter::from_spec(&spec).unwrap().matches(&keys));
+
+        // Events without the filtered account never match
+        let spec = FilterSpec { pools: vec![mint.to_string()], ..FilterSpec::default() };
+        assert!(!ClientFilter::from_spec(&spec).unwrap().matches(&keys));
+    }
+
+    #[test]
+    fn test_invalid_pubkey() {
+        let spec = FilterSpec { mints: vec!["not-a-key".to_string()], ..FilterSpec::default() };
+        assert!(ClientFilter::from_spec(&spec).is_err());
+    }
+}
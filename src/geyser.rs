@@ -0,0 +1,264 @@
+use crate::config::{EndpointConfig, GrpcConfig};
+use crate::upstream::{now_us, Feed, SubscriptionFilters, UpstreamStats};
+use anyhow::{Context, Result};
+use crossbeam_queue::ArrayQueue;
+use futures::StreamExt;
+use sol_parser_sdk::grpc::EventTypeFilter;
+use sol_parser_sdk::logs::optimized_matcher::detect_pumpfun_create;
+use sol_parser_sdk::DexEvent;
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::warn;
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::prelude::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterTransactions, SubscribeUpdateTransaction,
+};
+
+/// Events buffered per feed, the same as sol-parser-sdk's queue
+const QUEUE_CAPACITY: usize = 100_000;
+
+/// How often a full queue is reported while it keeps dropping events
+const DROP_WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// One Yellowstone subscription, decoded into its feed's event queue.
+///
+/// sol-parser-sdk's `subscribe_dex_events` streams in a task nobody can stop,
+/// so the proxy drives the Yellowstone client itself and decodes with the
+/// SDK's log parser. Dropping the stream aborts its task, which closes the
+/// gRPC stream and frees the queue once the consumer lets go of it.
+pub struct GeyserStream {
+    task: JoinHandle<()>,
+}
+
+impl GeyserStream {
+    /// Connect to `endpoint` and subscribe with `filters`. The queue fills
+    /// until the stream ends or is dropped, waking `feed`'s consumer; events
+    /// that don't fit are counted in `stats`.
+    pub async fn subscribe(
+        grpc: &GrpcConfig,
+        endpoint: &EndpointConfig,
+        filters: &SubscriptionFilters,
+        feed: Arc<Feed>,
+        stats: Arc<UpstreamStats>,
+    ) -> Result<(Self, Arc<ArrayQueue<DexEvent>>)> {
+        let mut builder = GeyserGrpcClient::build_from_shared(endpoint.endpoint.clone())
+            .context("Invalid gRPC endpoint")?
+            .x_token(endpoint.token.clone())
+            .context("Invalid gRPC token")?
+            .max_decoding_message_size(1024 * 1024 * 1024);
+        if endpoint.enable_tls.unwrap_or(grpc.enable_tls) {
+            builder = builder
+                .tls_config(ClientTlsConfig::new().with_native_roots())
+                .context("gRPC TLS setup failed")?;
+        }
+
+        let mut client = within_ms(grpc.connection_timeout_ms, builder.connect())
+            .await
+            .context("gRPC connection timed out")?
+            .context("gRPC connection failed")?;
+        let (sink, mut updates) = within_ms(
+            grpc.request_timeout_ms,
+            client.subscribe_with_request(Some(subscribe_request(filters))),
+        )
+        .await
+        .context("Subscription request timed out")?
+        .context("Subscription failed")?;
+
+        let queue = Arc::new(ArrayQueue::new(QUEUE_CAPACITY));
+        let producer = queue.clone();
+        let event_types = filters.event_types.clone();
+        let stream = Self::spawn(async move {
+            // Dropping the request sink would end the subscription
+            let _sink = sink;
+            let mut overflow = Overflow::new(stats);
+            while let Some(update) = updates.next().await {
+                match update {
+                    Ok(update) => {
+                        if let Some(UpdateOneof::Transaction(transaction)) = update.update_oneof {
+                            if let Some(event) = parse_transaction(&transaction, &event_types) {
+                                if producer.push(event).is_err() {
+                                    overflow.record();
+                                }
+                                feed.wake();
+                            }
+                        }
+                    }
+                    Err(status) => {
+                        warn!("⚠️  gRPC stream error: {}", status);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok((stream, queue))
+    }
+
+    pub(crate) fn spawn(task: impl Future<Output = ()> + Send + 'static) -> Self {
+        Self { task: tokio::spawn(task) }
+    }
+
+    /// Whether the stream failed or ended
+    pub fn is_closed(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for GeyserStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// `future`'s output unless it takes longer than `ms`, 0 for no limit
+async fn within_ms<F: Future>(ms: u64, future: F) -> Result<F::Output, tokio::time::error::Elapsed> {
+    match ms {
+        0 => Ok(future.await),
+        ms => tokio::time::timeout(Duration::from_millis(ms), future).await,
+    }
+}
+
+/// Counts the events a full queue turns away, warning at most once per
+/// `DROP_WARNING_INTERVAL`
+struct Overflow {
+    stats: Arc<UpstreamStats>,
+    unreported: u64,
+    warned_at: Option<Instant>,
+}
+
+impl Overflow {
+    fn new(stats: Arc<UpstreamStats>) -> Self {
+        Self {
+            stats,
+            unreported: 0,
+            warned_at: None,
+        }
+    }
+
+    fn record(&mut self) {
+        self.stats.record_ingest_dropped();
+        self.unreported += 1;
+        if self.warned_at.is_none_or(|at| at.elapsed() >= DROP_WARNING_INTERVAL) {
+            warn!(
+                "⚠️  Event queue full, dropped {} events; the consumer is falling behind the gRPC stream",
+                self.unreported
+            );
+            self.unreported = 0;
+            self.warned_at = Some(Instant::now());
+        }
+    }
+}
+
+/// The request sol-parser-sdk subscribes with: non-vote, successful
+/// transactions and accounts of the filtered programs, at `processed`
+fn subscribe_request(filters: &SubscriptionFilters) -> SubscribeRequest {
+    let accounts = HashMap::from([(
+        "account_filter_0".to_string(),
+        SubscribeRequestFilterAccounts {
+            account: filters.account.account.clone(),
+            owner: filters.account.owner.clone(),
+            ..Default::default()
+        },
+    )]);
+    let transactions = HashMap::from([(
+        "transaction_filter_0".to_string(),
+        SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            failed: Some(false),
+            account_include: filters.transaction.account_include.clone(),
+            account_exclude: filters.transaction.account_exclude.clone(),
+            account_required: filters.transaction.account_required.clone(),
+            ..Default::default()
+        },
+    )]);
+    SubscribeRequest {
+        accounts,
+        transactions,
+        commitment: Some(CommitmentLevel::Processed as i32),
+        ..Default::default()
+    }
+}
+
+/// Decode a transaction the way sol-parser-sdk's stream does: the first
+/// `Program data:` log that parses into an event of an included type
+fn parse_transaction(update: &SubscribeUpdateTransaction, event_types: &EventTypeFilter) -> Option<DexEvent> {
+    let grpc_recv_us = now_us();
+    let info = update.transaction.as_ref()?;
+    let logs = &info.meta.as_ref()?.log_messages;
+    let transaction = info.transaction.as_ref()?;
+    let message = transaction.message.as_ref()?;
+    let has_instruction = message
+        .instructions
+        .iter()
+        .any(|ix| (ix.program_id_index as usize) < message.account_keys.len());
+    if !has_instruction {
+        return None;
+    }
+
+    let signature = transaction
+        .signatures
+        .first()
+        .and_then(|sig| <[u8; 64]>::try_from(sig.as_slice()).ok())
+        .map(Signature::from)
+        .unwrap_or_default();
+    let block_time = Some(grpc_recv_us / 1_000_000);
+    let has_create = event_types.includes_pumpfun() && detect_pumpfun_create(logs);
+
+    logs.iter()
+        .filter(|log| log.contains("Program data: "))
+        .find_map(|log| {
+            sol_parser_sdk::logs::parse_log(
+                log,
+                signature,
+                update.slot,
+                info.index,
+                block_time,
+                grpc_recv_us,
+                Some(event_types),
+                has_create,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drop_aborts_the_stream() {
+        let queue = Arc::new(ArrayQueue::<DexEvent>::new(1));
+        let producer = queue.clone();
+        let stream = GeyserStream::spawn(async move {
+            let _producer = producer;
+            std::future::pending::<()>().await;
+        });
+        tokio::task::yield_now().await;
+        assert!(!stream.is_closed());
+        assert_eq!(Arc::strong_count(&queue), 2);
+
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while Arc::strong_count(&queue) > 1 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the stream task still holds the queue");
+    }
+
+    #[test]
+    fn test_overflow_counts_every_drop() {
+        let stats = Arc::new(UpstreamStats::default());
+        let mut overflow = Overflow::new(stats.clone());
+        overflow.record();
+        overflow.record();
+        overflow.record();
+        assert_eq!(stats.ingest_dropped(), 3);
+        // The first drop was reported, the rest wait for the next warning
+        assert_eq!(overflow.unreported, 2);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::metrics::ServerStats;
+use crate::subscriber::{EventStream, EventSubscription};
+use crate::ws_server::WsServer;
+use anyhow::{anyhow, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// A running proxy, returned by `ParserProxyServer::start`.
+///
+/// Dropping the handle leaves the server running in the background.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    ws_server: Arc<WsServer>,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<Result<()>>>,
+}
+
+impl ServerHandle {
+    pub(crate) fn new(
+        local_addr: SocketAddr,
+        ws_server: Arc<WsServer>,
+        shutdown: oneshot::Sender<()>,
+        task: JoinHandle<Result<()>>,
+    ) -> Self {
+        Self {
+            local_addr,
+            ws_server,
+            shutdown: Some(shutdown),
+            task: Some(task),
+        }
+    }
+
+    /// The address the WebSocket listener is bound to, with the actual port
+    /// when the config asked for port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Number of connected WebSocket clients
+    pub fn client_count(&self) -> usize {
+        self.ws_server.client_count()
+    }
+
+    /// Counters since the server started
+    pub fn stats(&self) -> ServerStats {
+        self.ws_server.stats()
+    }
+
+    /// Receive the parsed events matching `subscription` in this process
+    pub fn subscribe(&self, subscription: &EventSubscription) -> Result<EventStream> {
+        self.ws_server.subscribe(subscription)
+    }
+
+    /// Stop accepting connections and the gRPC consumers, drain the clients
+    /// as `ParserProxyServer::run` does on Ctrl+C, and wait until that is done
+    pub async fn shutdown(mut self) -> Result<()> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        self.join().await
+    }
+
+    /// Wait for the server to stop, which it only does on `shutdown` or when
+    /// the gRPC side fails. Cancel-safe, so it can be raced against other
+    /// futures and `shutdown` called afterwards.
+    pub async fn join(&mut self) -> Result<()> {
+        let Some(task) = self.task.as_mut() else {
+            return Ok(());
+        };
+        let result = task.await;
+        self.task = None;
+        result.map_err(|e| anyhow!("server task failed: {}", e))?
+    }
+}
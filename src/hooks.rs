@@ -0,0 +1,125 @@
+use sol_parser_sdk::DexEvent;
+use std::sync::Arc;
+
+/// Code run on every event in the consumer loop, after race-mode
+/// deduplication and before the event is broadcast.
+///
+/// For each hook in registration order, `inspect` sees the event first,
+/// then `keep` can drop it, `transform` can rewrite it and `enrich` can
+/// attach labels, which envelope clients receive in `labels`. A dropped
+/// event is not seen by later hooks and gets no sequence number. Hooks run
+/// on the latency-critical path, so they must not block.
+///
+/// ```rust
+/// use parser_proxy_ws::EventHook;
+/// use sol_parser_sdk::DexEvent;
+/// use solana_sdk::pubkey::Pubkey;
+/// use std::collections::HashSet;
+///
+/// struct Snipers(HashSet<Pubkey>);
+///
+/// impl EventHook for Snipers {
+///     fn enrich(&self, event: &DexEvent, labels: &mut Vec<String>) {
+///         if let DexEvent::PumpFunTrade(trade) = event {
+///             if self.0.contains(&trade.user) {
+///                 labels.push("known sniper wallet".to_string());
+///             }
+///         }
+///     }
+/// }
+/// ```
+pub trait EventHook: Send + Sync {
+    fn inspect(&self, _event: &DexEvent) {}
+
+    /// `false` drops the event
+    fn keep(&self, _event: &DexEvent) -> bool {
+        true
+    }
+
+    fn transform(&self, _event: &mut DexEvent) {}
+
+    fn enrich(&self, _event: &DexEvent, _labels: &mut Vec<String>) {}
+}
+
+/// The hooks registered on a server, shared by its consumers
+#[derive(Clone, Default)]
+pub struct Hooks(Arc<[Arc<dyn EventHook>]>);
+
+impl Hooks {
+    pub fn new(hooks: Vec<Arc<dyn EventHook>>) -> Self {
+        Self(hooks.into())
+    }
+
+    /// Run every hook on the event; `false` when one of them dropped it
+    #[inline]
+    pub fn run(&self, event: &mut DexEvent, labels: &mut Vec<String>) -> bool {
+        for hook in self.0.iter() {
+            hook.inspect(event);
+            if !hook.keep(event) {
+                return false;
+            }
+            hook.transform(event);
+            hook.enrich(event, labels);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::block_meta;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn slot(event: &DexEvent) -> u64 {
+        match event {
+            DexEvent::BlockMeta(e) => e.metadata.slot,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Drops odd slots and doubles the rest
+    struct Even;
+
+    impl EventHook for Even {
+        fn keep(&self, event: &DexEvent) -> bool {
+            slot(event).is_multiple_of(2)
+        }
+
+        fn transform(&self, event: &mut DexEvent) {
+            if let DexEvent::BlockMeta(e) = event {
+                e.metadata.slot *= 2;
+            }
+        }
+    }
+
+    /// Labels the slot it sees and counts events
+    #[derive(Default)]
+    struct Label(AtomicUsize);
+
+    impl EventHook for Label {
+        fn inspect(&self, _event: &DexEvent) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn enrich(&self, event: &DexEvent, labels: &mut Vec<String>) {
+            labels.push(format!("slot {}", slot(event)));
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let label = Arc::new(Label::default());
+        let hooks = Hooks::new(vec![Arc::new(Even), label.clone()]);
+
+        let mut event = block_meta(2);
+        let mut labels = Vec::new();
+        assert!(hooks.run(&mut event, &mut labels));
+        assert_eq!(slot(&event), 4);
+        assert_eq!(labels, ["slot 4"]);
+
+        // Later hooks don't see a dropped event
+        assert!(!hooks.run(&mut block_meta(3), &mut Vec::new()));
+        assert_eq!(label.0.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,169 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio_tungstenite::tungstenite::handshake::server::Request;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+
+/// Largest request head accepted before the WebSocket upgrade
+const MAX_HEAD_LEN: usize = 16 * 1024;
+const MAX_HEADERS: usize = 64;
+
+/// The first request on a connection
+pub enum Head {
+    /// A WebSocket upgrade; the bytes read so far must be replayed to the handshake
+    Upgrade(Vec<u8>),
+    /// A plain HTTP request, answered with one response before closing
+    Request(Request),
+    /// Unparseable or oversized, to be answered with this status
+    Invalid(StatusCode),
+}
+
+/// Read the request head so that plain HTTP requests can be told apart
+/// from WebSocket upgrades; tungstenite rejects the former before any
+/// callback sees them
+pub async fn read_head<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Head> {
+    let mut buf = Vec::with_capacity(1024);
+    loop {
+        let mut chunk = [0u8; 1024];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buf) {
+            Ok(httparse::Status::Complete(_)) => {
+                let upgrade = request.headers.iter().any(|header| {
+                    header.name.eq_ignore_ascii_case("upgrade")
+                        && String::from_utf8_lossy(header.value)
+                            .to_ascii_lowercase()
+                            .contains("websocket")
+                });
+                if upgrade {
+                    return Ok(Head::Upgrade(buf));
+                }
+                let mut builder = Request::builder()
+                    .method(request.method.unwrap_or_default())
+                    .uri(request.path.unwrap_or_default());
+                for header in request.headers.iter() {
+                    builder = builder.header(header.name, header.value);
+                }
+                return Ok(match builder.body(()) {
+                    Ok(request) => Head::Request(request),
+                    Err(_) => Head::Invalid(StatusCode::BAD_REQUEST),
+                });
+            }
+            Ok(httparse::Status::Partial) if buf.len() < MAX_HEAD_LEN => {}
+            Ok(httparse::Status::Partial) | Err(httparse::Error::TooManyHeaders) => {
+                return Ok(Head::Invalid(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE));
+            }
+            Err(_) => return Ok(Head::Invalid(StatusCode::BAD_REQUEST)),
+        }
+    }
+}
+
+pub const JSON: &str = "application/json";
+pub const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4";
+
+/// A response to a plain HTTP request
+pub struct Response {
+    pub status: StatusCode,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: StatusCode, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: JSON,
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Write the response and close the connection
+pub async fn respond<S: AsyncWrite + Unpin>(stream: &mut S, response: &Response, with_body: bool) -> io::Result<()> {
+    let status = response.status;
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: {}\r\ncontent-length: {}\r\ncache-control: no-store\r\nconnection: close\r\n\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default(),
+        response.content_type,
+        response.body.len()
+    );
+    if with_body {
+        head.push_str(&response.body);
+    }
+    stream.write_all(head.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// A stream that first yields bytes already read from it
+pub struct Prefixed<S> {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: S,
+}
+
+impl<S> Prefixed<S> {
+    pub fn new(prefix: Vec<u8>, inner: S) -> Self {
+        Self { prefix, pos: 0, inner }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Prefixed<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if self.pos < self.prefix.len() {
+            let n = (self.prefix.len() - self.pos).min(buf.remaining());
+            buf.put_slice(&self.prefix[self.pos..self.pos + n]);
+            self.pos += n;
+            if self.pos == self.prefix.len() {
+                self.prefix = Vec::new();
+                self.pos = 0;
+            }
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Prefixed<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_head() {
+        let request = b"GET /readyz?verbose=1 HTTP/1.1\r\nHost: localhost\r\nX-API-Key: secret\r\n\r\n";
+        let Head::Request(request) = read_head(&mut &request[..]).await.unwrap() else {
+            panic!("expected a request");
+        };
+        assert_eq!((request.method().as_str(), request.uri().path()), ("GET", "/readyz"));
+        assert_eq!(request.headers()["x-api-key"], "secret");
+
+        let upgrade = b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: WebSocket\r\n\r\nextra";
+        let Head::Upgrade(bytes) = read_head(&mut &upgrade[..]).await.unwrap() else {
+            panic!("expected an upgrade");
+        };
+        let mut replayed = Vec::new();
+        Prefixed::new(bytes, &b""[..]).read_to_end(&mut replayed).await.unwrap();
+        assert_eq!(replayed, upgrade);
+    }
+}
@@ -12,56 +12,109 @@
 //!
 //! ## Usage
 //!
-//! ```rust
+//! ```rust,no_run
 //! use parser_proxy_ws::ParserProxyServer;
 //!
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
 //!     // Start the parser proxy server with a config file
 //!     let server = ParserProxyServer::new("config.toml")?;
-//!     server.start().await?;
+//!     server.run().await?;
 //!     Ok(())
 //! }
 //! ```
 
-use sol_parser_sdk::grpc::{
-    AccountFilter, ClientConfig, EventTypeFilter, TransactionFilter,
-    YellowstoneGrpc,
-};
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
-use anyhow::Result;
+use anyhow::{Context, Result};
 
+mod auth;
+mod client_queue;
 mod config;
+mod dedup;
+mod envelope;
+mod event_types;
+mod filter;
+mod geyser;
+mod handle;
+mod hooks;
+mod http;
+mod metrics;
+mod normalize;
+mod output;
+mod pipeline;
+mod protocol;
+mod reload;
+mod replay;
+mod subscriber;
+#[cfg(test)]
+pub(crate) mod test_support;
+mod tls;
+mod upstream;
 mod ws_server;
 
-pub use config::Config;
-use ws_server::WsServer;
+pub use config::{Config, EventsConfig};
+pub use envelope::Envelope;
+pub use filter::FilterSpec;
+pub use handle::ServerHandle;
+pub use hooks::EventHook;
+pub use metrics::ServerStats;
+pub use normalize::{Dex, NormalizedEvent, NormalizedTrade, TradeSide};
+pub use output::{Encoding, IntFormat, KeyFormat, OutputFormat};
+pub use subscriber::{EventStream, EventSubscription};
+pub use upstream::UpstreamStats;
+pub use ws_server::WsServer;
+
+use hooks::Hooks;
+use reload::ConfigLoader;
 
 /// The main parser proxy server that handles gRPC subscriptions and WebSocket broadcasting
 pub struct ParserProxyServer {
     config: Config,
+    hooks: Vec<Arc<dyn EventHook>>,
+    reload: Option<(PathBuf, ConfigLoader)>,
 }
 
 impl ParserProxyServer {
     /// Create a new parser proxy server with the specified config file path
     ///
+    /// Problems in the config are logged as warnings, and a file that can't
+    /// be read or parsed falls back to `Config::default()`; use `new_strict`
+    /// to refuse both. The file is read once; see `reload_from` to pick up
+    /// changes while the server runs.
+    ///
     /// # Arguments
     ///
     /// * `config_path` - Path to the TOML configuration file
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// use parser_proxy_ws::ParserProxyServer;
     ///
     /// let server = ParserProxyServer::new("config.toml")?;
+    /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn new<P: AsRef<std::path::Path>>(config_path: P) -> Result<Self> {
+    pub fn new<P: AsRef<Path>>(config_path: P) -> Result<Self> {
         let config = Config::load_or_default(config_path.as_ref().to_str().unwrap());
 
-        Ok(Self { config })
+        Ok(Self::with_config(config))
+    }
+
+    /// Create a new parser proxy server from a config file that must exist,
+    /// parse, and pass `Config::load_strict`'s checks: no unknown keys, no
+    /// gRPC endpoint on the proxy's own port, and no events of a protocol
+    /// switched off in `[protocols]`
+    pub fn new_strict<P: AsRef<Path>>(config_path: P) -> Result<Self> {
+        let config = Config::load_strict(&config_path)?;
+
+        Ok(Self::with_config(config))
     }
 
     /// Create a new parser proxy server with an existing config
@@ -70,7 +123,66 @@ impl ParserProxyServer {
     ///
     /// * `config` - Pre-loaded configuration
     pub fn with_config(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            hooks: Vec::new(),
+            reload: None,
+        }
+    }
+
+    /// Reload the config with `load` on SIGHUP, and when `config_path`
+    /// changes if `server.config_reload_interval_ms` is set to check it
+    ///
+    /// Changes to `[events]` and `[protocols]` resubscribe the gRPC feeds
+    /// without disconnecting WebSocket clients; other changes need a restart.
+    /// A file that fails to load is logged and the running config is kept.
+    /// This installs a process-wide SIGHUP handler, so only call it when the
+    /// server owns the process's SIGHUP.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use parser_proxy_ws::{Config, ParserProxyServer};
+    ///
+    /// let config = Config::load_from_file("config.toml")?;
+    /// let server = ParserProxyServer::with_config(config).reload_from("config.toml", |path| {
+    ///     let mut config = Config::load_from_file(path)?;
+    ///     config.apply_env_overrides()?;
+    ///     Ok(config)
+    /// });
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn reload_from<P, F>(mut self, config_path: P, load: F) -> Self
+    where
+        P: AsRef<Path>,
+        F: Fn(&Path) -> Result<Config> + Send + Sync + 'static,
+    {
+        self.reload = Some((config_path.as_ref().to_path_buf(), Arc::new(load)));
+        self
+    }
+
+    /// Register an event hook; hooks run in the order they are added
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use parser_proxy_ws::{EventHook, ParserProxyServer};
+    /// use sol_parser_sdk::DexEvent;
+    ///
+    /// struct NoBlockMeta;
+    ///
+    /// impl EventHook for NoBlockMeta {
+    ///     fn keep(&self, event: &DexEvent) -> bool {
+    ///         !matches!(event, DexEvent::BlockMeta(_))
+    ///     }
+    /// }
+    ///
+    /// let server = ParserProxyServer::new("config.toml")?.with_hook(NoBlockMeta);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_hook(mut self, hook: impl EventHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
     }
 
     /// Start the parser proxy server
@@ -81,59 +193,119 @@ impl ParserProxyServer {
     /// 3. Subscribe to configured DEX events
     /// 4. Start broadcasting events to WebSocket clients
     ///
-    /// The method will run indefinitely until interrupted.
+    /// If the gRPC stream fails, or goes quiet for `grpc.stall_timeout_ms`
+    /// when that is set, the subscription is rebuilt with jittered
+    /// exponential backoff.
+    ///
+    /// Returns once the listener is bound, with a handle to the running
+    /// server; `run` adds Ctrl+C and SIGTERM handling on top.
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```rust,no_run
     /// use parser_proxy_ws::ParserProxyServer;
     ///
     /// #[tokio::main]
     /// async fn main() -> anyhow::Result<()> {
     ///     let server = ParserProxyServer::new("config.toml")?;
-    ///     server.start().await?;
-    ///     Ok(())
+    ///     let handle = server.start().await?;
+    ///     println!("listening on {}", handle.local_addr());
+    ///
+    ///     tokio::signal::ctrl_c().await?;
+    ///     handle.shutdown().await
     /// }
     /// ```
-    pub async fn start(self) -> Result<()> {
+    pub async fn start(self) -> Result<ServerHandle> {
         self.init_tracing();
 
         info!("🚀 Starting Parser Proxy WebSocket Server...");
 
-        let ws_server = Arc::new(WsServer::new());
+        let mut ws_server = WsServer::new(&self.config.server);
+        if let Some(tls) = &self.config.server.tls {
+            ws_server = ws_server.with_tls(tls)?;
+        }
+        if let Some(auth) = &self.config.server.auth {
+            ws_server = ws_server.with_auth(auth)?;
+        }
+        let event_types = self.config.get_enabled_event_types();
+        let ws_server = ws_server.with_streams(
+            self.config.get_enabled_protocol_names(),
+            event_types.iter().map(|t| event_types::event_type_name(*t)).collect(),
+        );
+        let ws_server = Arc::new(ws_server);
         let ws_server_clone = ws_server.clone();
 
         // Start WebSocket server
         let addr = format!("{}:{}", self.config.server.host, self.config.server.port);
-        let listener = TcpListener::bind(&addr).await?;
-        info!("📡 WebSocket server listening on: ws://{}", addr);
+        let listener = TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("Failed to listen on {}", addr))?;
+        let local_addr = listener.local_addr()?;
+        let scheme = if self.config.server.tls.is_some() { "wss" } else { "ws" };
+        info!("📡 WebSocket server listening on: {}://{}", scheme, local_addr);
 
-        tokio::spawn(async move {
+        let accept_task = tokio::spawn(async move {
             ws_server_clone.run(listener).await;
         });
 
-        // Start gRPC client and event processing
-        self.start_grpc_processing(ws_server).await?;
-
-        Ok(())
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let shutdown = async {
+            // A dropped handle leaves the server running
+            if shutdown_rx.await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        };
+        let task = tokio::spawn(self.serve(ws_server.clone(), accept_task, shutdown));
+        Ok(ServerHandle::new(local_addr, ws_server, shutdown_tx, task))
     }
 
     /// Start the parser proxy server and wait for shutdown signal
     ///
-    /// This is a convenience method that calls `start()` and waits for Ctrl+C.
+    /// On Ctrl+C or SIGTERM, stops accepting connections and the gRPC
+    /// subscription, then gives every client up to `server.shutdown_drain_ms`
+    /// to receive its queued messages before closing it with `1001 Going Away`.
     pub async fn run(self) -> Result<()> {
-        let server_task = tokio::spawn(async move {
-            if let Err(e) = self.start().await {
-                error!("Server error: {}", e);
+        let mut handle = self.start().await?;
+        tokio::select! {
+            result = handle.join() => return result,
+            _ = shutdown_signal() => {}
+        }
+        handle.shutdown().await
+    }
+
+    /// Run the gRPC side until `shutdown` resolves, then drain the clients
+    async fn serve(
+        self,
+        ws_server: Arc<WsServer>,
+        accept_task: JoinHandle<()>,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let (filters_tx, filters) = watch::channel(reload::subscription_filters(&self.config));
+        let watch_config = async {
+            match self.reload.clone() {
+                Some((path, load)) => {
+                    reload::watch_config(path, load, self.config.clone(), filters_tx, ws_server.clone()).await
+                }
+                // Keep the sender so the feeds don't see a closed channel
+                None => std::future::pending::<()>().await,
             }
-        });
+        };
 
-        info!("🛑 Press Ctrl+C to stop...");
-        tokio::signal::ctrl_c().await?;
-        info!("👋 Shutting down gracefully...");
+        // Dropping the upstream future stops the consumers
+        let upstream = self.start_grpc_processing(ws_server.clone(), filters);
+        let result = tokio::select! {
+            result = upstream => result,
+            _ = watch_config => Ok(()),
+            _ = shutdown => Ok(()),
+        };
 
-        server_task.abort();
-        Ok(())
+        info!("👋 Shutting down gracefully...");
+        ws_server
+            .shutdown(Duration::from_millis(self.config.server.shutdown_drain_ms))
+            .await;
+        let _ = accept_task.await;
+        info!("✅ Shutdown complete");
+        result
     }
 
     /// Get the server configuration
@@ -141,9 +313,11 @@ impl ParserProxyServer {
         &self.config
     }
 
-    /// Get the WebSocket URL that clients can connect to
+    /// Get the WebSocket URL that clients can connect to; `wss://` when
+    /// `[server.tls]` is configured
     pub fn ws_url(&self) -> String {
-        format!("ws://{}:{}", self.config.server.host, self.config.server.port)
+        let scheme = if self.config.server.tls.is_some() { "wss" } else { "ws" };
+        format!("{}://{}:{}", scheme, self.config.server.host, self.config.server.port)
     }
 
     fn init_tracing(&self) {
@@ -167,86 +341,50 @@ impl ParserProxyServer {
     async fn start_grpc_processing(
         &self,
         ws_server: Arc<WsServer>,
+        filters: watch::Receiver<Option<upstream::SubscriptionFilters>>,
     ) -> Result<()> {
-        let mut grpc_config = ClientConfig::default();
-        grpc_config.enable_metrics = self.config.grpc.enable_metrics;
-        grpc_config.connection_timeout_ms = self.config.grpc.connection_timeout_ms;
-        grpc_config.request_timeout_ms = self.config.grpc.request_timeout_ms;
-        grpc_config.enable_tls = self.config.grpc.enable_tls;
-
-        let grpc = YellowstoneGrpc::new_with_config(
-            self.config.grpc.endpoint.clone(),
-            self.config.grpc.token.clone(),
-            grpc_config,
+        // Runs until the process stops, resubscribing whenever the stream
+        // dies or a reload changes the filters
+        upstream::run(
+            self.config.grpc.clone(),
+            self.config.pipeline.clone(),
+            filters,
+            ws_server,
+            Hooks::new(self.hooks.clone()),
         )
-        .map_err(|e| anyhow::anyhow!("gRPC client creation failed: {}", e))?;
-
-        info!("✅ gRPC client created successfully");
+        .await
+    }
+}
 
-        let protocols = self.config.get_enabled_protocols();
-        if protocols.is_empty() {
-            warn!("⚠️  No protocols enabled in config, server will receive no events");
-            return Ok(());
+/// Resolves on Ctrl+C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("⚠️  Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
         }
-
-        info!("📊 Monitoring protocols: {:?}", protocols);
-
-        let transaction_filter = TransactionFilter::for_protocols(&protocols);
-        let account_filter = AccountFilter::for_protocols(&protocols);
-
-        let event_types = self.config.get_enabled_event_types();
-        if event_types.is_empty() {
-            warn!("⚠️  No event types enabled in config, server will receive no events");
-            return Ok(());
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("❌ Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
         }
+    };
 
-        info!("🎯 Monitoring event types: {:?}", event_types);
-        let event_filter = EventTypeFilter::include_only(event_types);
-
-        info!("🎧 Starting subscription...");
-
-        let queue = grpc
-            .subscribe_dex_events(
-                vec![transaction_filter],
-                vec![account_filter],
-                Some(event_filter),
-            )
-            .await
-            .map_err(|e| anyhow::anyhow!("Subscription failed: {}", e))?;
-
-        info!("✅ Subscription established");
-
-        tokio::spawn(async move {
-            let mut spin_count = 0u32;
-            loop {
-                if let Some(event) = queue.pop() {
-                    spin_count = 0;
-
-                    let event_json = match serde_json::to_string(&event) {
-                        Ok(json) => json,
-                        Err(e) => {
-                            error!("Failed to serialize event: {}", e);
-                            continue;
-                        }
-                    };
-
-                    ws_server.broadcast(&event_json).await;
-                } else {
-                    spin_count += 1;
-                    if spin_count < 1000 {
-                        std::hint::spin_loop();
-                    } else {
-                        tokio::task::yield_now().await;
-                        spin_count = 0;
-                    }
-                }
-            }
-        });
-
-        // Keep the main task alive
-        loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-        }
+    info!("🛑 Press Ctrl+C to stop...");
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => info!("📴 Received SIGTERM"),
     }
 }
 
@@ -258,7 +396,7 @@ impl ParserProxyServer {
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,no_run
 /// use parser_proxy_ws::run_server;
 ///
 /// #[tokio::main]
@@ -267,14 +405,14 @@ impl ParserProxyServer {
 ///     Ok(())
 /// }
 /// ```
-pub async fn run_server<P: AsRef<std::path::Path>>(config_path: P) -> Result<()> {
+pub async fn run_server<P: AsRef<Path>>(config_path: P) -> Result<()> {
     let server = ParserProxyServer::new(config_path)?;
     server.run().await
 }
 
 /// Re-export commonly used types for convenience
 pub mod prelude {
-    pub use crate::{ParserProxyServer, Config, run_server};
+    pub use crate::{ParserProxyServer, ServerHandle, Config, run_server};
     pub use sol_parser_sdk::core::events::*;
     pub use sol_parser_sdk::grpc::EventType;
 }
@@ -320,4 +458,41 @@ pumpfun_trade = true
         // Cleanup
         fs::remove_file("test_config.toml").unwrap();
     }
+
+    #[tokio::test]
+    async fn test_server_handle() {
+        use futures_util::StreamExt;
+
+        let mut config = Config::default();
+        config.server.port = 0;
+        config.protocols.pumpfun = None;
+        config.events = EventsConfig::Patterns(Vec::new());
+        let handle = ParserProxyServer::with_config(config).start().await.unwrap();
+        assert_ne!(handle.local_addr().port(), 0);
+
+        let url = format!("ws://{}", handle.local_addr());
+        let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        while handle.client_count() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(handle.stats().clients, 1);
+
+        handle.shutdown().await.unwrap();
+        assert!(matches!(ws.next().await, Some(Ok(tokio_tungstenite::tungstenite::Message::Close(_)))));
+    }
+
+    #[test]
+    fn test_ws_url_wss() {
+        let mut config = Config::default();
+        config.server.tls = Some(config::TlsConfig {
+            cert_path: "cert.pem".to_string(),
+            key_path: "key.pem".to_string(),
+            reload_interval_ms: 10_000,
+        });
+        let server = ParserProxyServer::with_config(config);
+        assert_eq!(
+            server.ws_url(),
+            format!("wss://{}:{}", server.config().server.host, server.config().server.port)
+        );
+    }
 }
\ No newline at end of file
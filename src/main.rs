@@ -1,12 +1,121 @@
-use parser_proxy_ws::run_server;
-use std::env;
+use clap::{Arg, ArgAction, Command};
+use parser_proxy_ws::{Config, ParserProxyServer};
+use std::path::Path;
 
-/// Binary entry point - simply delegates to the library's run_server function
+/// Binary entry point - loads the config, applies overrides and delegates to
+/// the library's `ParserProxyServer`
+///
+/// Overrides are applied in this order, each winning over the previous one:
+/// the config file, `PPWS_<SECTION>__<KEY>` environment variables, and the
+/// command line. On SIGHUP, edits to `[events]` and `[protocols]` in the
+/// config file are applied without a restart, as they are when
+/// `server.config_reload_interval_ms` is set to watch the file.
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let config_path = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "config.toml".to_string());
+    let args = cli().get_matches();
 
-    run_server(config_path).await
-}
\ No newline at end of file
+    let config_path = args
+        .get_one::<String>("config")
+        .or(args.get_one::<String>("config_path"))
+        .map(String::as_str)
+        .unwrap_or("config.toml");
+    let strict = args.get_flag("strict");
+
+    // Each override keeps the flag it came from for error messages
+    let mut overrides = Vec::new();
+    for (flag, key) in [
+        ("host", "server.host"),
+        ("port", "server.port"),
+        ("grpc_endpoint", "grpc.endpoint"),
+        ("grpc_token", "grpc.token"),
+        ("events", "events"),
+    ] {
+        if let Some(value) = args.get_one::<String>(flag) {
+            overrides.push((format!("--{}", flag.replace('_', "-")), key.to_string(), value.clone()));
+        }
+    }
+    for set in args.get_many::<String>("set").into_iter().flatten() {
+        let Some((key, value)) = set.split_once('=') else {
+            anyhow::bail!("--set expects KEY=VALUE, got {}", set);
+        };
+        overrides.push(("--set".to_string(), key.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut config = if strict {
+        Config::load_strict(config_path)?
+    } else {
+        Config::load_or_default(config_path)
+    };
+    config.apply_env_overrides()?;
+    config.apply_sourced_overrides(overrides.clone())?;
+
+    if args.get_flag("print_config") {
+        print!("{}", config.to_redacted_toml()?);
+        return Ok(());
+    }
+    if strict {
+        config.validate()?;
+    }
+
+    // Reloads go through the same overrides, so they keep winning over the file
+    let load = move |path: &Path| {
+        let mut config = if strict {
+            Config::load_strict(path)?
+        } else {
+            Config::load_from_file(path)?
+        };
+        config.apply_env_overrides()?;
+        config.apply_sourced_overrides(overrides.clone())?;
+        if strict {
+            config.validate()?;
+        }
+        Ok(config)
+    };
+    ParserProxyServer::with_config(config)
+        .reload_from(config_path, load)
+        .run()
+        .await
+}
+
+fn cli() -> Command {
+    Command::new("parser-proxy-ws")
+        .about("Streams Solana DEX events from Yellowstone gRPC to WebSocket clients")
+        .arg(Arg::new("config_path").value_name("CONFIG").help("Config file [default: config.toml]"))
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("PATH")
+                .conflicts_with("config_path")
+                .help("Config file, same as the CONFIG argument"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .help("Refuse to start when the config file is missing or has problems"),
+        )
+        .arg(
+            Arg::new("print_config")
+                .long("print-config")
+                .action(ArgAction::SetTrue)
+                .help("Print the effective config with secrets redacted and exit"),
+        )
+        .arg(Arg::new("host").long("host").value_name("HOST").help("server.host"))
+        .arg(Arg::new("port").long("port").value_name("PORT").help("server.port"))
+        .arg(Arg::new("grpc_endpoint").long("grpc-endpoint").value_name("URL").help("grpc.endpoint"))
+        .arg(Arg::new("grpc_token").long("grpc-token").value_name("TOKEN").help("grpc.token"))
+        .arg(
+            Arg::new("events")
+                .long("events")
+                .value_name("LIST")
+                .help("Event types and patterns, comma-separated, e.g. PumpFunTrade,RaydiumClmm*"),
+        )
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .help("Override any key by its dotted path, e.g. grpc.stall_timeout_ms=30000"),
+        )
+}
@@ -0,0 +1,277 @@
+use crate::event_types::{protocol_name, table_index, EVENT_TYPES};
+use crate::output::Encoding;
+use crate::upstream::UpstreamStats;
+use sol_parser_sdk::grpc::EventType;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Upper bounds of the latency histogram buckets, in microseconds
+const LATENCY_BUCKETS_US: [u64; 14] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000,
+];
+
+/// Counters for the Prometheus `/metrics` endpoint; all updates are relaxed
+/// atomic adds so the broadcast path never waits on a scrape
+pub struct Metrics {
+    /// Per `EVENT_TYPES` entry, then one for types the table is missing
+    events_received: [AtomicU64; EVENT_TYPES.len() + 1],
+    events_broadcast: AtomicU64,
+    messages_queued: AtomicU64,
+    dropped_messages: AtomicU64,
+    rate_limited_messages: AtomicU64,
+    serialization_errors: [AtomicU64; Encoding::COUNT],
+    enqueue_latency: Histogram,
+    send_latency: Histogram,
+}
+
+/// A point-in-time copy of the server's counters, for embedders that don't
+/// scrape `/metrics`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    pub clients: usize,
+    /// Events taken off the parser queue, of all types
+    pub events_received: u64,
+    pub events_broadcast: u64,
+    pub messages_queued: u64,
+    pub dropped_messages: u64,
+    pub rate_limited_messages: u64,
+    pub connected_feeds: usize,
+    pub reconnects: u64,
+}
+
+/// Fan-out state read at scrape time
+pub struct Snapshot<'a> {
+    pub upstream: &'a UpstreamStats,
+    /// Queue depth of every connected client
+    pub queue_depths: Vec<usize>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            events_received: [const { AtomicU64::new(0) }; EVENT_TYPES.len() + 1],
+            events_broadcast: AtomicU64::new(0),
+            messages_queued: AtomicU64::new(0),
+            dropped_messages: AtomicU64::new(0),
+            rate_limited_messages: AtomicU64::new(0),
+            serialization_errors: [const { AtomicU64::new(0) }; Encoding::COUNT],
+            enqueue_latency: Histogram::default(),
+            send_latency: Histogram::default(),
+        }
+    }
+}
+
+impl Metrics {
+    #[inline]
+    pub fn event_received(&self, event_type: EventType) {
+        let index = table_index(event_type).unwrap_or(EVENT_TYPES.len());
+        self.events_received[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An event was offered to `messages` client queues, `latency_us` after
+    /// the gRPC client received it
+    #[inline]
+    pub fn event_broadcast(&self, messages: u64, latency_us: Option<i64>) {
+        if messages > 0 {
+            self.events_broadcast.fetch_add(1, Ordering::Relaxed);
+            self.messages_queued.fetch_add(messages, Ordering::Relaxed);
+        }
+        if let Some(latency_us) = latency_us {
+            self.enqueue_latency.observe(latency_us.max(0) as u64);
+        }
+    }
+
+    /// An event's frame was written to a client's socket, `latency_us` after
+    /// the gRPC client received it, including its time in the client's queue
+    #[inline]
+    pub fn event_sent(&self, latency_us: i64) {
+        self.send_latency.observe(latency_us.max(0) as u64);
+    }
+
+    #[inline]
+    pub fn message_dropped(&self) {
+        self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn message_rate_limited(&self) {
+        self.rate_limited_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn serialization_error(&self, encoding: Encoding) {
+        self.serialization_errors[encoding as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self, clients: usize, upstream: &UpstreamStats) -> ServerStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ServerStats {
+            clients,
+            events_received: self.events_received.iter().map(load).sum(),
+            events_broadcast: load(&self.events_broadcast),
+            messages_queued: load(&self.messages_queued),
+            dropped_messages: load(&self.dropped_messages),
+            rate_limited_messages: load(&self.rate_limited_messages),
+            connected_feeds: upstream.connected_feeds(),
+            reconnects: upstream.reconnects(),
+        }
+    }
+
+    /// Render in the Prometheus text exposition format
+    pub fn render(&self, snapshot: &Snapshot) -> String {
+        let mut out = String::with_capacity(4096);
+
+        header(&mut out, "parser_proxy_events_received_total", "counter", "Events taken off the parser queue");
+        let names = EVENT_TYPES
+            .iter()
+            .map(|(name, event_type)| (*name, protocol_name(*event_type)))
+            .chain([("Unknown", "unknown")]);
+        for ((name, protocol), counter) in names.zip(&self.events_received) {
+            let count = counter.load(Ordering::Relaxed);
+            if count > 0 {
+                let _ = writeln!(
+                    out,
+                    "parser_proxy_events_received_total{{event_type=\"{}\",protocol=\"{}\"}} {}",
+                    name, protocol, count
+                );
+            }
+        }
+
+        counter(&mut out, "parser_proxy_events_broadcast_total", "Events queued for at least one client", &self.events_broadcast);
+        counter(&mut out, "parser_proxy_messages_queued_total", "Event frames offered to client queues", &self.messages_queued);
+        counter(&mut out, "parser_proxy_dropped_messages_total", "Messages dropped by slow-consumer policies", &self.dropped_messages);
+        counter(&mut out, "parser_proxy_rate_limited_messages_total", "Messages withheld by API key rate limits", &self.rate_limited_messages);
+
+        header(&mut out, "parser_proxy_serialization_errors_total", "counter", "Events that failed to encode");
+        for encoding in [Encoding::Json, Encoding::MessagePack, Encoding::Cbor, Encoding::Bincode] {
+            let count = self.serialization_errors[encoding as usize].load(Ordering::Relaxed);
+            let _ = writeln!(out, "parser_proxy_serialization_errors_total{{encoding=\"{}\"}} {}", encoding.name(), count);
+        }
+
+        // Aggregated rather than one series per client id, which would grow
+        // with every connection ever made
+        let depths = &snapshot.queue_depths;
+        gauge(&mut out, "parser_proxy_clients", "Connected WebSocket clients", depths.len() as u64);
+        gauge(
+            &mut out,
+            "parser_proxy_client_queue_depth_max",
+            "Messages waiting in the fullest client queue",
+            depths.iter().max().copied().unwrap_or(0) as u64,
+        );
+        gauge(
+            &mut out,
+            "parser_proxy_client_queue_depth_sum",
+            "Messages waiting across all client queues",
+            depths.iter().sum::<usize>() as u64,
+        );
+
+        let upstream = snapshot.upstream;
+        gauge(&mut out, "parser_proxy_upstream_connected_feeds", "Subscribed gRPC feeds", upstream.connected_feeds() as u64);
+        header(&mut out, "parser_proxy_upstream_reconnects_total", "counter", "gRPC resubscriptions");
+        let _ = writeln!(out, "parser_proxy_upstream_reconnects_total {}", upstream.reconnects());
+        header(&mut out, "parser_proxy_ingest_dropped_total", "counter", "Events dropped because the gRPC event queue was full");
+        let _ = writeln!(out, "parser_proxy_ingest_dropped_total {}", upstream.ingest_dropped());
+
+        self.enqueue_latency.render(
+            &mut out,
+            "parser_proxy_event_enqueue_latency_seconds",
+            "Time from gRPC receive (metadata.grpc_recv_us) until the event is queued for clients, excluding time spent in client queues",
+        );
+        self.send_latency.render(
+            &mut out,
+            "parser_proxy_event_send_latency_seconds",
+            "Time from gRPC receive (metadata.grpc_recv_us) until the event is written to a client's socket, once per client",
+        );
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: &AtomicU64) {
+    header(out, name, "counter", help);
+    let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// A fixed-bucket histogram of microsecond values, exported in seconds
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS_US.len()],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS_US.len()],
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    #[inline]
+    fn observe(&self, value_us: u64) {
+        if let Some(i) = LATENCY_BUCKETS_US.iter().position(|bound| value_us <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(value_us, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        header(out, name, "histogram", help);
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS_US.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, *bound as f64 / 1e6, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum_us.load(Ordering::Relaxed) as f64 / 1e6);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.event_received(EventType::PumpFunTrade);
+        metrics.event_broadcast(3, Some(120));
+        metrics.event_broadcast(0, Some(5_000_000));
+        metrics.message_dropped();
+        metrics.event_sent(30_000);
+
+        let upstream = UpstreamStats::default();
+        let text = metrics.render(&Snapshot {
+            upstream: &upstream,
+            queue_depths: vec![12, 3],
+        });
+
+        assert!(text.contains("parser_proxy_events_received_total{event_type=\"PumpFunTrade\",protocol=\"pumpfun\"} 1"));
+        assert!(text.contains("parser_proxy_messages_queued_total 3"));
+        assert!(text.contains("parser_proxy_dropped_messages_total 1"));
+        assert!(text.contains("parser_proxy_clients 2"));
+        assert!(text.contains("parser_proxy_client_queue_depth_max 12"));
+        assert!(text.contains("parser_proxy_client_queue_depth_sum 15"));
+        assert!(text.contains("parser_proxy_ingest_dropped_total 0"));
+        assert!(!text.contains("client=\""));
+        assert!(text.contains("parser_proxy_event_enqueue_latency_seconds_bucket{le=\"0.00025\"} 1"));
+        assert!(text.contains("parser_proxy_event_enqueue_latency_seconds_bucket{le=\"+Inf\"} 2"));
+        assert!(text.contains("parser_proxy_event_send_latency_seconds_bucket{le=\"0.025\"} 0"));
+        assert!(text.contains("parser_proxy_event_send_latency_seconds_bucket{le=\"0.05\"} 1"));
+        assert!(!text.contains("BlockMeta"));
+    }
+}
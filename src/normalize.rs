@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use sol_parser_sdk::core::events::EventMetadata;
+use sol_parser_sdk::DexEvent;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+/// Wire name clients subscribe to for the normalized trade stream; frames are
+/// sent as `{"NormalizedTrade": {...}}`, the same shape as raw events
+pub const NORMALIZED_TRADE: &str = "NormalizedTrade";
+
+const WSOL_MINT: Pubkey = solana_sdk::pubkey!("So11111111111111111111111111111111111111112");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dex {
+    #[serde(rename = "pumpfun")]
+    PumpFun,
+    #[serde(rename = "pumpswap")]
+    PumpSwap,
+    Bonk,
+    RaydiumCpmm,
+    RaydiumClmm,
+    RaydiumAmmV4,
+    OrcaWhirlpool,
+    MeteoraPools,
+    MeteoraDammV2,
+}
+
+/// Direction of a trade from the trader's point of view on the base token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+    /// The event does not say which way the swap went; `base` is then the
+    /// input token, `quote` the output token and there is no price
+    Unknown,
+}
+
+/// Derived events, serialized externally tagged like `DexEvent`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NormalizedEvent {
+    NormalizedTrade(NormalizedTrade),
+}
+
+/// One swap in a protocol-independent shape.
+///
+/// For pair pools without known mints, base is token A / token 0 / X and quote
+/// is token B / token 1 / Y. Amounts are raw token units and `price` is
+/// `quote_amount / base_amount` in those units, not adjusted for decimals.
+/// Fields the source event does not carry are `None`; that includes the
+/// price of a trade whose side is unknown, since it would flip between `x`
+/// and `1/x` with the swap direction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NormalizedTrade {
+    pub dex: Dex,
+    pub pool: Option<Pubkey>,
+    pub base_mint: Option<Pubkey>,
+    pub quote_mint: Option<Pubkey>,
+    pub side: TradeSide,
+    /// `None` when the event only carries the instruction's limit
+    pub base_amount: Option<u64>,
+    pub quote_amount: Option<u64>,
+    pub trader: Option<Pubkey>,
+    pub signature: Signature,
+    pub slot: u64,
+    pub price: Option<f64>,
+}
+
+/// The normalized trade for a swap event, or `None` for every other event
+pub fn normalize_trade(event: &DexEvent) -> Option<NormalizedTrade> {
+    let trade = match event {
+        DexEvent::PumpFunTrade(e) => Trade::new(Dex::PumpFun, &e.metadata)
+            .mints(e.mint, WSOL_MINT)
+            .side(e.is_buy, e.token_amount, e.sol_amount)
+            .trader(e.user),
+        DexEvent::PumpSwapBuy(e) => Trade::new(Dex::PumpSwap, &e.metadata)
+            .pool(e.pool_id)
+            .mints(e.token_mint, WSOL_MINT)
+            .side(true, e.token_amount, e.sol_amount)
+            .trader(e.user),
+        DexEvent::PumpSwapSell(e) => Trade::new(Dex::PumpSwap, &e.metadata)
+            .pool(e.pool_id)
+            .mints(e.token_mint, WSOL_MINT)
+            .side(false, e.token_amount, e.sol_amount)
+            .trader(e.user),
+        DexEvent::BonkTrade(e) => {
+            let (base, quote) = if e.is_buy {
+                (e.amount_out, e.amount_in)
+            } else {
+                (e.amount_in, e.amount_out)
+            };
+            Trade::new(Dex::Bonk, &e.metadata)
+                .pool(e.pool_state)
+                .side(e.is_buy, base, quote)
+                .trader(e.user)
+        }
+        // `base_input` tells exact-input from exact-output swaps and the
+        // vault fields are balances, so neither says which token went in
+        DexEvent::RaydiumCpmmSwap(e) => Trade::new(Dex::RaydiumCpmm, &e.metadata)
+            .pool(e.pool_id)
+            .unknown_side(Some(e.input_amount), Some(e.output_amount)),
+        DexEvent::RaydiumClmmSwap(e) => Trade::new(Dex::RaydiumClmm, &e.metadata)
+            .pool(e.pool_state)
+            .side(!e.zero_for_one, e.amount_0, e.amount_1)
+            .trader(e.sender),
+        // The SDK reports the instruction's parameters: the exact side of the
+        // swap is known, the other is only a limit (`minimum_amount_out` /
+        // `max_amount_in`) and is left out
+        DexEvent::RaydiumAmmV4Swap(e) => {
+            let (input, output) = if e.amount_in > 0 {
+                (Some(e.amount_in), None)
+            } else {
+                (None, Some(e.amount_out))
+            };
+            Trade::new(Dex::RaydiumAmmV4, &e.metadata)
+                .pool(e.amm)
+                .unknown_side(input, output)
+                .trader(e.user_source_owner)
+        }
+        DexEvent::OrcaWhirlpoolSwap(e) => {
+            let (base, quote) = if e.a_to_b {
+                (e.input_amount, e.output_amount)
+            } else {
+                (e.output_amount, e.input_amount)
+            };
+            Trade::new(Dex::OrcaWhirlpool, &e.metadata)
+                .pool(e.whirlpool)
+                .side(!e.a_to_b, base, quote)
+        }
+        DexEvent::MeteoraPoolsSwap(e) => Trade::new(Dex::MeteoraPools, &e.metadata)
+            .unknown_side(Some(e.in_amount), Some(e.out_amount)),
+        DexEvent::MeteoraDammV2Swap(e) => {
+            let (base, quote) = if e.swap_for_y {
+                (e.amount_in, e.amount_out)
+            } else {
+                (e.amount_out, e.amount_in)
+            };
+            Trade::new(Dex::MeteoraDammV2, &e.metadata)
+                .pool(e.lb_pair)
+                .side(!e.swap_for_y, base, quote)
+                .trader(e.from)
+        }
+        _ => return None,
+    };
+    Some(trade.0)
+}
+
+/// Builder used by `normalize_trade`; default pubkeys, which the SDK uses
+/// for accounts it could not resolve, are treated as unknown
+struct Trade(NormalizedTrade);
+
+impl Trade {
+    fn new(dex: Dex, metadata: &EventMetadata) -> Self {
+        Self(NormalizedTrade {
+            dex,
+            pool: None,
+            base_mint: None,
+            quote_mint: None,
+            side: TradeSide::Unknown,
+            base_amount: None,
+            quote_amount: None,
+            trader: None,
+            signature: metadata.signature,
+            slot: metadata.slot,
+            price: None,
+        })
+    }
+
+    fn pool(mut self, pool: Pubkey) -> Self {
+        self.0.pool = known(pool);
+        self
+    }
+
+    fn mints(mut self, base: Pubkey, quote: Pubkey) -> Self {
+        self.0.base_mint = known(base);
+        self.0.quote_mint = known(quote);
+        self
+    }
+
+    fn trader(mut self, trader: Pubkey) -> Self {
+        self.0.trader = known(trader);
+        self
+    }
+
+    fn side(mut self, is_buy: bool, base_amount: u64, quote_amount: u64) -> Self {
+        self.0.side = if is_buy { TradeSide::Buy } else { TradeSide::Sell };
+        self.0.base_amount = Some(base_amount);
+        self.0.quote_amount = Some(quote_amount);
+        self.0.price = (base_amount > 0).then(|| quote_amount as f64 / base_amount as f64);
+        self
+    }
+
+    fn unknown_side(mut self, input_amount: Option<u64>, output_amount: Option<u64>) -> Self {
+        self.0.side = TradeSide::Unknown;
+        self.0.base_amount = input_amount;
+        self.0.quote_amount = output_amount;
+        self
+    }
+}
+
+fn known(pubkey: Pubkey) -> Option<Pubkey> {
+    (pubkey != Pubkey::default()).then_some(pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sol_parser_sdk::core::events::{
+        MeteoraPoolsSwapEvent, OrcaWhirlpoolSwapEvent, PumpSwapSellEvent, RaydiumAmmV4SwapEvent,
+    };
+
+    fn metadata() -> EventMetadata {
+        EventMetadata {
+            signature: Signature::from([1u8; 64]),
+            slot: 42,
+            tx_index: 0,
+            block_time_us: 0,
+            grpc_recv_us: 0,
+        }
+    }
+
+    #[test]
+    fn test_pumpswap_sell() {
+        let mint = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let event = DexEvent::PumpSwapSell(PumpSwapSellEvent {
+            metadata: metadata(),
+            pool_id: Pubkey::default(),
+            user,
+            token_mint: mint,
+            token_amount: 2_000,
+            sol_amount: 500,
+            price: 0,
+            slippage: 0,
+        });
+
+        let trade = normalize_trade(&event).unwrap();
+        assert_eq!(trade.dex, Dex::PumpSwap);
+        assert_eq!(trade.pool, None);
+        assert_eq!(trade.base_mint, Some(mint));
+        assert_eq!(trade.quote_mint, Some(WSOL_MINT));
+        assert_eq!(trade.side, TradeSide::Sell);
+        assert_eq!((trade.base_amount, trade.quote_amount), (Some(2_000), Some(500)));
+        assert_eq!(trade.trader, Some(user));
+        assert_eq!(trade.slot, 42);
+        assert_eq!(trade.price, Some(0.25));
+    }
+
+    #[test]
+    fn test_orca_b_to_a_is_a_buy() {
+        let event = DexEvent::OrcaWhirlpoolSwap(OrcaWhirlpoolSwapEvent {
+            metadata: metadata(),
+            whirlpool: Pubkey::new_unique(),
+            a_to_b: false,
+            pre_sqrt_price: 0,
+            post_sqrt_price: 0,
+            input_amount: 300,
+            output_amount: 100,
+            input_transfer_fee: 0,
+            output_transfer_fee: 0,
+            lp_fee: 0,
+            protocol_fee: 0,
+        });
+
+        let trade = normalize_trade(&event).unwrap();
+        assert_eq!(trade.side, TradeSide::Buy);
+        assert_eq!((trade.base_amount, trade.quote_amount), (Some(100), Some(300)));
+        assert_eq!(trade.price, Some(3.0));
+        assert!(normalize_trade(&DexEvent::Error("x".into())).is_none());
+    }
+
+    #[test]
+    fn test_unknown_side_has_no_price() {
+        let event = DexEvent::MeteoraPoolsSwap(MeteoraPoolsSwapEvent {
+            metadata: metadata(),
+            in_amount: 300,
+            out_amount: 100,
+            trade_fee: 0,
+            admin_fee: 0,
+            host_fee: 0,
+        });
+
+        let trade = normalize_trade(&event).unwrap();
+        assert_eq!(trade.side, TradeSide::Unknown);
+        assert_eq!((trade.base_amount, trade.quote_amount), (Some(300), Some(100)));
+        assert_eq!(trade.price, None);
+    }
+
+    #[test]
+    fn test_amm_v4_leaves_out_limits() {
+        let event = DexEvent::RaydiumAmmV4Swap(RaydiumAmmV4SwapEvent {
+            metadata: metadata(),
+            amount_in: 1_000,
+            minimum_amount_out: 900,
+            max_amount_in: 0,
+            amount_out: 0,
+            token_program: Pubkey::default(),
+            amm: Pubkey::default(),
+            amm_authority: Pubkey::default(),
+            amm_open_orders: Pubkey::default(),
+            amm_target_orders: None,
+            pool_coin_token_account: Pubkey::default(),
+            pool_pc_token_account: Pubkey::default(),
+            serum_program: Pubkey::default(),
+            serum_market: Pubkey::default(),
+            serum_bids: Pubkey::default(),
+            serum_asks: Pubkey::default(),
+            serum_event_queue: Pubkey::default(),
+            serum_coin_vault_account: Pubkey::default(),
+            serum_pc_vault_account: Pubkey::default(),
+            serum_vault_signer: Pubkey::default(),
+            user_source_token_account: Pubkey::default(),
+            user_destination_token_account: Pubkey::default(),
+            user_source_owner: Pubkey::default(),
+        });
+
+        let trade = normalize_trade(&event).unwrap();
+        assert_eq!((trade.base_amount, trade.quote_amount), (Some(1_000), None));
+        assert_eq!(trade.price, None);
+    }
+}
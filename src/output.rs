@@ -0,0 +1,681 @@
+use serde::de::value::Error as ValueError;
+use serde::ser::{
+    self, Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+use serde::Deserialize;
+
+/// WebSocket frame encoding of events. JSON is sent as text frames, the
+/// others as binary frames; control replies are always JSON text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Json,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+    Cbor,
+    /// bincode 1.x with its default options. It is not self-describing, so
+    /// the `OutputFormat` is ignored and events keep their serde shape.
+    Bincode,
+}
+
+impl Encoding {
+    pub const COUNT: usize = 4;
+
+    /// Names accepted as WebSocket subprotocols and as `?encoding=`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::MessagePack => "msgpack",
+            Self::Cbor => "cbor",
+            Self::Bincode => "bincode",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Json, Self::MessagePack, Self::Cbor, Self::Bincode]
+            .into_iter()
+            .find(|encoding| encoding.name() == name)
+    }
+
+    /// The `encoding` parameter of the connect-time query string, if present
+    pub fn from_query(query: &str) -> Result<Option<Self>, String> {
+        for pair in query.split('&') {
+            if let Some(("encoding", value)) = pair.split_once('=') {
+                return Self::from_name(value)
+                    .map(Some)
+                    .ok_or_else(|| format!("invalid encoding: {}", value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// How pubkeys and signatures are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyFormat {
+    /// Byte arrays, as serde writes them
+    #[default]
+    Bytes,
+    Base58,
+}
+
+/// How u64, u128 and i128 values are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntFormat {
+    #[default]
+    Number,
+    /// Decimal strings, so JavaScript clients don't lose precision
+    String,
+}
+
+/// A client's choice of JSON output format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputFormat {
+    #[serde(default)]
+    pub keys: KeyFormat,
+    #[serde(default)]
+    pub integers: IntFormat,
+    /// Wrap each event in an `Envelope` with a sequence number and proxy timestamps
+    #[serde(default)]
+    pub envelope: bool,
+}
+
+impl OutputFormat {
+    /// Number of distinct formats, see `index`
+    pub const COUNT: usize = 8;
+
+    pub fn index(self) -> usize {
+        self.keys as usize * 4 + self.integers as usize * 2 + self.envelope as usize
+    }
+
+    /// Whether keys and integers are written as serde writes them
+    fn is_plain(self) -> bool {
+        self.keys == KeyFormat::Bytes && self.integers == IntFormat::Number
+    }
+
+    /// Parse the connect-time query string, e.g. `keys=base58&integers=string`.
+    /// Unrelated parameters are ignored.
+    pub fn from_query(query: &str) -> Result<Self, String> {
+        let mut format = Self::default();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "keys" => format.keys = parse_value(key, value)?,
+                "integers" => format.integers = parse_value(key, value)?,
+                "envelope" => {
+                    format.envelope = match value {
+                        "" | "true" | "1" => true,
+                        "false" | "0" => false,
+                        _ => return Err(format!("invalid envelope value: {}", value)),
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(format)
+    }
+}
+
+fn parse_value<T: for<'de> Deserialize<'de>>(key: &str, value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("invalid {} format: {}", key, value))
+}
+
+/// Serialize `value` as JSON in the given format
+pub fn to_json<T: Serialize + ?Sized>(value: &T, format: OutputFormat) -> serde_json::Result<String> {
+    if format.is_plain() {
+        return serde_json::to_string(value);
+    }
+    serde_json::to_string(&Formatted { value, format })
+}
+
+/// Serialize `value` with one of the binary encodings
+pub fn to_binary<T: Serialize + ?Sized>(
+    value: &T,
+    encoding: Encoding,
+    format: OutputFormat,
+) -> anyhow::Result<Vec<u8>> {
+    let formatted = Formatted { value, format };
+    let bytes = match encoding {
+        Encoding::MessagePack if format.is_plain() => rmp_serde::to_vec_named(value)?,
+        Encoding::MessagePack => rmp_serde::to_vec_named(&formatted)?,
+        Encoding::Cbor => {
+            let mut bytes = Vec::new();
+            if format.is_plain() {
+                ciborium::into_writer(value, &mut bytes)?;
+            } else {
+                ciborium::into_writer(&formatted, &mut bytes)?;
+            }
+            bytes
+        }
+        Encoding::Bincode => bincode::serialize(value)?,
+        Encoding::Json => to_json(value, format)?.into_bytes(),
+    };
+    Ok(bytes)
+}
+
+/// Newtype names solana-sdk uses for 32-byte pubkeys and 64-byte signatures
+const KEY_TYPES: &[&str] = &["Address", "Pubkey", "Signature"];
+
+struct Formatted<'a, T: ?Sized> {
+    value: &'a T,
+    format: OutputFormat,
+}
+
+impl<T: Serialize + ?Sized> Serialize for Formatted<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(Adapter {
+            inner: serializer,
+            format: self.format,
+        })
+    }
+}
+
+/// Forwards to the wrapped serializer, rewriting keys and large integers
+struct Adapter<S> {
+    inner: S,
+    format: OutputFormat,
+}
+
+impl<S> Adapter<S> {
+    fn wrap<'a, T: ?Sized>(&self, value: &'a T) -> Formatted<'a, T> {
+        Formatted { value, format: self.format }
+    }
+
+    fn strings(&self) -> bool {
+        self.format.integers == IntFormat::String
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<S::Ok, S::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for Adapter<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+    forward! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> {
+        if self.strings() {
+            self.inner.serialize_str(&v.to_string())
+        } else {
+            self.inner.serialize_u64(v)
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> {
+        if self.strings() {
+            self.inner.serialize_str(&v.to_string())
+        } else {
+            self.inner.serialize_u128(v)
+        }
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> {
+        if self.strings() {
+            self.inner.serialize_str(&v.to_string())
+        } else {
+            self.inner.serialize_i128(v)
+        }
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_some(&value)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        if self.format.keys == KeyFormat::Base58 && KEY_TYPES.contains(&name) {
+            let mut bytes = ByteCollector(Vec::with_capacity(64));
+            if value.serialize(&mut bytes).is_ok() {
+                return self.inner.serialize_str(&bs58::encode(bytes.0).into_string());
+            }
+        }
+        let value = self.wrap(value);
+        self.inner.serialize_newtype_struct(name, &value)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_newtype_variant(name, index, variant, &value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        let format = self.format;
+        Ok(Compound { inner: self.inner.serialize_seq(len)?, format })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        let format = self.format;
+        Ok(Compound { inner: self.inner.serialize_tuple(len)?, format })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        let format = self.format;
+        Ok(Compound { inner: self.inner.serialize_tuple_struct(name, len)?, format })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_tuple_variant(name, index, variant, len)?,
+            format,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        let format = self.format;
+        Ok(Compound { inner: self.inner.serialize_map(len)?, format })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        let format = self.format;
+        Ok(Compound { inner: self.inner.serialize_struct(name, len)?, format })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_struct_variant(name, index, variant, len)?,
+            format,
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct Compound<C> {
+    inner: C,
+    format: OutputFormat,
+}
+
+impl<C> Compound<C> {
+    fn wrap<'a, T: ?Sized>(&self, value: &'a T) -> Formatted<'a, T> {
+        Formatted { value, format: self.format }
+    }
+}
+
+impl<C: SerializeSeq> SerializeSeq for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_element(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTuple> SerializeTuple for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_element(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleStruct> SerializeTupleStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleVariant> SerializeTupleVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeMap> SerializeMap for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+        let key = self.wrap(key);
+        self.inner.serialize_key(&key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_value(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStruct> SerializeStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStructVariant> SerializeStructVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+/// Captures the bytes of a fixed-size byte array; fails on anything else
+struct ByteCollector(Vec<u8>);
+
+fn not_bytes() -> ValueError {
+    ser::Error::custom("not a byte array")
+}
+
+macro_rules! reject {
+    ($($method:ident($($ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<$ret, ValueError> {
+                Err(not_bytes())
+            }
+        )*
+    };
+}
+
+impl Serializer for &mut ByteCollector {
+    type Ok = ();
+    type Error = ValueError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), ValueError>;
+    type SerializeTupleVariant = Impossible<(), ValueError>;
+    type SerializeMap = Impossible<(), ValueError>;
+    type SerializeStruct = Impossible<(), ValueError>;
+    type SerializeStructVariant = Impossible<(), ValueError>;
+
+    fn serialize_u8(self, v: u8) -> Result<(), ValueError> {
+        self.0.push(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), ValueError> {
+        self.0.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, ValueError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, ValueError> {
+        Ok(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, value: &T) -> Result<(), ValueError> {
+        value.serialize(self)
+    }
+
+    reject! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<(), ValueError> {
+        Err(not_bytes())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), ValueError> {
+        Err(not_bytes())
+    }
+}
+
+impl SerializeSeq for &mut ByteCollector {
+    type Ok = ();
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ValueError> {
+        Ok(())
+    }
+}
+
+impl SerializeTuple for &mut ByteCollector {
+    type Ok = ();
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ValueError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::{Envelope, Header};
+    use crate::test_support::signed_block_meta;
+    use sol_parser_sdk::DexEvent;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signature;
+
+    #[test]
+    fn test_base58_and_string_integers() {
+        let signature = Signature::from([3u8; 64]);
+        let event = signed_block_meta(signature, u64::MAX);
+        let format = OutputFormat {
+            keys: KeyFormat::Base58,
+            integers: IntFormat::String,
+            ..Default::default()
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&event, format).unwrap()).unwrap();
+        let metadata = &json["BlockMeta"]["metadata"];
+        assert_eq!(metadata["signature"], signature.to_string());
+        assert_eq!(metadata["slot"], u64::MAX.to_string());
+        assert_eq!(metadata["grpc_recv_us"], 3);
+
+        let pubkey = Pubkey::new_unique();
+        let json = to_json(&Some(pubkey), format).unwrap();
+        assert_eq!(json, format!("\"{}\"", pubkey));
+    }
+
+    #[test]
+    fn test_binary_encodings_round_trip() {
+        let event = signed_block_meta(Signature::from([9u8; 64]), 7);
+        let format = OutputFormat::default();
+
+        let bytes = to_binary(&event, Encoding::MessagePack, format).unwrap();
+        let decoded: DexEvent = rmp_serde::from_slice(&bytes).unwrap();
+        assert!(matches!(decoded, DexEvent::BlockMeta(e) if e.metadata.slot == 7));
+
+        let bytes = to_binary(&event, Encoding::Cbor, format).unwrap();
+        let decoded: DexEvent = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert!(matches!(decoded, DexEvent::BlockMeta(e) if e.metadata.slot == 7));
+
+        let bytes = to_binary(&event, Encoding::Bincode, format).unwrap();
+        let decoded: DexEvent = bincode::deserialize(&bytes).unwrap();
+        assert!(matches!(decoded, DexEvent::BlockMeta(e) if e.metadata.tx_index == 1));
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let event = signed_block_meta(Signature::from([9u8; 64]), 7);
+        let header = Header {
+            seq: 42,
+            event_type: "BlockMeta",
+            protocol: "solana",
+            proxy_recv_us: 4,
+        };
+        let format = OutputFormat {
+            envelope: true,
+            ..Default::default()
+        };
+
+        let labels = ["sniper".to_string()];
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&header.wrap(&event, &labels, 5), format).unwrap()).unwrap();
+        assert_eq!(json["seq"], 42);
+        assert_eq!(json["type"], "BlockMeta");
+        assert_eq!(json["labels"], serde_json::json!(["sniper"]));
+        assert_eq!(json["event"]["BlockMeta"]["metadata"]["slot"], 7);
+
+        let bytes = to_binary(&header.wrap(&event, &labels, 5), Encoding::Bincode, format).unwrap();
+        let decoded: Envelope<DexEvent> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!((decoded.seq, decoded.proxy_send_us), (42, 5));
+        assert_eq!(decoded.labels, labels);
+        assert!(matches!(decoded.event, DexEvent::BlockMeta(e) if e.metadata.slot == 7));
+    }
+
+    #[test]
+    fn test_from_query() {
+        let format = OutputFormat::from_query("token=abc&keys=base58").unwrap();
+        assert_eq!(format.keys, KeyFormat::Base58);
+        assert_eq!(format.integers, IntFormat::Number);
+        assert!(!format.envelope);
+        assert!(OutputFormat::from_query("integers=hex").is_err());
+        assert!(OutputFormat::from_query("envelope").unwrap().envelope);
+        assert!(!OutputFormat::from_query("envelope=false").unwrap().envelope);
+
+        assert_eq!(Encoding::from_query("keys=base58&encoding=cbor"), Ok(Some(Encoding::Cbor)));
+        assert_eq!(Encoding::from_query("keys=base58"), Ok(None));
+        assert!(Encoding::from_query("encoding=xml").is_err());
+    }
+}
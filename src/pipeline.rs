@@ -0,0 +1,236 @@
+use crate::config::{PipelineConfig, WaitStrategy};
+use crate::hooks::Hooks;
+use crate::upstream::Feed;
+use crate::ws_server::WsServer;
+use crossbeam_queue::ArrayQueue;
+use sol_parser_sdk::DexEvent;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Start the task that drains the feed's event queue into the WebSocket server.
+/// The consumer exits once the feed is stopped, e.g. when its subscription is replaced.
+pub fn spawn_consumer(
+    queue: Arc<ArrayQueue<DexEvent>>,
+    ws_server: Arc<WsServer>,
+    config: &PipelineConfig,
+    feed: Arc<Feed>,
+) {
+    let config = config.clone();
+    info!("⚙️  Consumer wait strategy: {:?}", config.wait_strategy);
+
+    match config.wait_strategy {
+        WaitStrategy::BusySpin | WaitStrategy::SpinThenPark => {
+            std::thread::Builder::new()
+                .name("ppws-consumer".to_string())
+                .spawn(move || {
+                    pin_to_core(config.cpu_core);
+                    consume_on_thread(&queue, &ws_server, &config, &feed);
+                })
+                .expect("failed to spawn consumer thread");
+        }
+        WaitStrategy::Poll => {
+            if config.cpu_core.is_some() {
+                warn!("⚠️  cpu_core is ignored by the poll wait strategy");
+            }
+            if config.park_timeout_us < 1000 {
+                warn!(
+                    "⚠️  The poll wait strategy sleeps at least 1ms, park_timeout_us = {} is rounded up",
+                    config.park_timeout_us
+                );
+            }
+            tokio::spawn(async move {
+                consume_on_runtime(&queue, &ws_server, &config, &feed).await;
+            });
+        }
+        WaitStrategy::Notify => {
+            if config.cpu_core.is_some() {
+                warn!("⚠️  cpu_core is ignored by the notify wait strategy");
+            }
+            tokio::spawn(async move {
+                consume_on_notify(&queue, &ws_server, &feed).await;
+            });
+        }
+    }
+}
+
+fn consume_on_thread(
+    queue: &ArrayQueue<DexEvent>,
+    ws_server: &WsServer,
+    config: &PipelineConfig,
+    feed: &Feed,
+) {
+    let park_timeout = Duration::from_micros(config.park_timeout_us);
+    let mut spin_count = 0u32;
+
+    while !feed.is_stopped() {
+        if let Some(event) = queue.pop() {
+            spin_count = 0;
+            if feed.accept(&event) {
+                forward(ws_server, feed.hooks(), event);
+            }
+            continue;
+        }
+
+        if config.wait_strategy == WaitStrategy::BusySpin || spin_count < config.spin_iterations {
+            spin_count = spin_count.saturating_add(1);
+            std::hint::spin_loop();
+        } else {
+            std::thread::park_timeout(park_timeout);
+        }
+    }
+}
+
+async fn consume_on_runtime(
+    queue: &ArrayQueue<DexEvent>,
+    ws_server: &WsServer,
+    config: &PipelineConfig,
+    feed: &Feed,
+) {
+    // The tokio timer wheel has 1ms resolution
+    let poll_interval = Duration::from_micros(config.park_timeout_us).max(Duration::from_millis(1));
+
+    while !feed.is_stopped() {
+        while let Some(event) = queue.pop() {
+            if feed.accept(&event) {
+                forward(ws_server, feed.hooks(), event);
+            }
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+async fn consume_on_notify(queue: &ArrayQueue<DexEvent>, ws_server: &WsServer, feed: &Feed) {
+    while !feed.is_stopped() {
+        while let Some(event) = queue.pop() {
+            if feed.accept(&event) {
+                forward(ws_server, feed.hooks(), event);
+            }
+        }
+        feed.woken().await;
+    }
+}
+
+/// Run the event hooks and broadcast the event unless one of them dropped it
+#[inline]
+fn forward(ws_server: &WsServer, hooks: &Hooks, mut event: DexEvent) {
+    let mut labels = Vec::new();
+    if hooks.run(&mut event, &mut labels) {
+        ws_server.broadcast_labeled_event(&event, &labels);
+    }
+}
+
+fn pin_to_core(cpu_core: Option<usize>) {
+    let Some(core) = cpu_core else {
+        return;
+    };
+
+    let pinned = core_affinity::get_core_ids()
+        .and_then(|ids| ids.into_iter().find(|id| id.id == core))
+        .map(core_affinity::set_for_current)
+        .unwrap_or(false);
+
+    if pinned {
+        info!("📌 Consumer thread pinned to core {}", core);
+    } else {
+        warn!("⚠️  Failed to pin consumer thread to core {}", core);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::test_support::block_meta;
+    use std::time::Instant;
+
+    fn filled_queue(events: u64) -> Arc<ArrayQueue<DexEvent>> {
+        let queue = Arc::new(ArrayQueue::new(16));
+        for slot in 0..events {
+            queue.push(block_meta(slot)).unwrap();
+        }
+        queue
+    }
+
+    fn wait_until_empty(queue: &ArrayQueue<DexEvent>) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !queue.is_empty() {
+            assert!(Instant::now() < deadline, "consumer did not drain the queue");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_thread_consumer_drains_until_stopped() {
+        let config = Config::default();
+        let ws_server = Arc::new(WsServer::new(&config.server));
+        let feed = Arc::new(Feed::new(None, Hooks::default()));
+        let queue = filled_queue(3);
+
+        let consumer = {
+            let (queue, ws_server, feed) = (queue.clone(), ws_server.clone(), feed.clone());
+            std::thread::spawn(move || consume_on_thread(&queue, &ws_server, &config.pipeline, &feed))
+        };
+
+        wait_until_empty(&queue);
+        feed.stop();
+        consumer.join().unwrap();
+        assert!(ws_server.upstream().last_event_age_us().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_poll_consumer_drains_until_stopped() {
+        let config = Config::default();
+        let pipeline = PipelineConfig {
+            wait_strategy: WaitStrategy::Poll,
+            ..config.pipeline
+        };
+        let ws_server = Arc::new(WsServer::new(&config.server));
+        let feed = Arc::new(Feed::new(None, Hooks::default()));
+        let queue = filled_queue(3);
+
+        let consumer = {
+            let (queue, ws_server, feed) = (queue.clone(), ws_server.clone(), feed.clone());
+            tokio::spawn(async move { consume_on_runtime(&queue, &ws_server, &pipeline, &feed).await })
+        };
+
+        while !queue.is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        feed.stop();
+        tokio::time::timeout(Duration::from_secs(1), consumer).await.unwrap().unwrap();
+        assert!(ws_server.upstream().last_event_age_us().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_notify_consumer_wakes_on_push() {
+        let config = Config::default();
+        let ws_server = Arc::new(WsServer::new(&config.server));
+        let feed = Arc::new(Feed::new(None, Hooks::default()));
+        let queue = filled_queue(0);
+
+        let consumer = {
+            let (queue, ws_server, feed) = (queue.clone(), ws_server.clone(), feed.clone());
+            tokio::spawn(async move { consume_on_notify(&queue, &ws_server, &feed).await })
+        };
+
+        // Idle until woken: a push without a wakeup stays queued
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        queue.push(block_meta(1)).unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(queue.len(), 1);
+
+        feed.wake();
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !queue.is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        feed.stop();
+        tokio::time::timeout(Duration::from_secs(1), consumer).await.unwrap().unwrap();
+        assert!(ws_server.upstream().last_event_age_us().is_some());
+    }
+}
@@ -0,0 +1,83 @@
+use crate::config::SlowConsumerPolicy;
+use crate::filter::FilterSpec;
+use crate::output::OutputFormat;
+use serde::{Deserialize, Serialize};
+
+/// Control messages a client may send as WebSocket text frames
+///
+/// ```json
+/// {"action": "subscribe", "event_types": ["PumpFunTrade", "PumpFunCreate"]}
+/// {"action": "unsubscribe", "event_types": ["PumpFunCreate"]}
+/// {"action": "list_subscriptions"}
+/// {"action": "set_filter", "mints": ["..."], "wallets": ["..."]}
+/// {"action": "clear_filter"}
+/// {"action": "set_policy", "policy": "drop_newest"}
+/// {"action": "set_format", "keys": "base58", "integers": "string"}
+/// {"action": "status"}
+/// {"action": "resume", "from_seq": 1024}
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientRequest {
+    Subscribe { event_types: Vec<String> },
+    Unsubscribe { event_types: Vec<String> },
+    ListSubscriptions,
+    SetFilter {
+        #[serde(flatten)]
+        filter: FilterSpec,
+    },
+    ClearFilter,
+    SetPolicy { policy: SlowConsumerPolicy },
+    SetFormat {
+        #[serde(flatten)]
+        format: OutputFormat,
+    },
+    Status,
+    /// Replay the buffered events from `from_seq` that were sent before this
+    /// connection was opened
+    Resume { from_seq: u64 },
+}
+
+/// Messages sent by the server, in reply to a `ClientRequest` or unprompted
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The client's current subscription set and filter after the request was applied
+    Subscriptions {
+        event_types: Vec<&'static str>,
+        filter: FilterSpec,
+    },
+    /// The client's slow-consumer policy after a `set_policy` request
+    Policy { policy: SlowConsumerPolicy },
+    /// The client's output format after a `set_format` request
+    Format {
+        #[serde(flatten)]
+        format: OutputFormat,
+    },
+    /// Messages were dropped because the client fell behind
+    Lagged { dropped: u64, total_dropped: u64 },
+    /// Messages were withheld because the API key's message rate was exceeded
+    RateLimited { dropped: u64 },
+    /// The gRPC upstream went down or came back; also the reply to `status`
+    Upstream { connected: bool, reconnects: u64 },
+    /// Sent after the replayed events of a `resume`; `complete` is false
+    /// when events from `from_seq` onwards had already left the buffer
+    Resumed {
+        from_seq: u64,
+        through_seq: u64,
+        replayed: usize,
+        complete: bool,
+    },
+    Error { message: String },
+}
+
+impl ServerMessage {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::Error { message: message.into() }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Serializing these plain enums cannot fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
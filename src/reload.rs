@@ -0,0 +1,203 @@
+use crate::config::Config;
+use crate::event_types;
+use crate::upstream::SubscriptionFilters;
+use crate::ws_server::WsServer;
+use anyhow::Result;
+use sol_parser_sdk::grpc::{AccountFilter, EventTypeFilter, TransactionFilter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Loads the config file again, with whatever overrides the server was
+/// started with
+pub type ConfigLoader = Arc<dyn Fn(&Path) -> Result<Config> + Send + Sync>;
+
+/// The gRPC filters for the enabled protocols and event types, `None` when
+/// there is nothing to subscribe to
+pub fn subscription_filters(config: &Config) -> Option<SubscriptionFilters> {
+    let program_ids = config.get_enabled_program_ids();
+    if program_ids.is_empty() {
+        warn!("⚠️  No protocols enabled in config, server will receive no events");
+        return None;
+    }
+    let event_types = config.get_enabled_event_types();
+    if event_types.is_empty() {
+        warn!("⚠️  No event types enabled in config, server will receive no events");
+        return None;
+    }
+
+    info!("📊 Monitoring protocols: {:?}", config.get_enabled_protocol_names());
+    info!("🎯 Monitoring event types: {:?}", event_types);
+    Some(SubscriptionFilters {
+        transaction: TransactionFilter::from_program_ids(program_ids.clone()),
+        account: AccountFilter::from_program_owners(program_ids),
+        event_types: EventTypeFilter::include_only(event_types),
+    })
+}
+
+/// Reloads the config file on SIGHUP, or when its modification time
+/// changes if `server.config_reload_interval_ms` is set to check it.
+///
+/// Only `[events]` and `[protocols]` take effect: when they change what is
+/// subscribed to, the new filters are sent to the upstream feeds, which
+/// resubscribe while clients stay connected. A file that fails to load
+/// keeps the running config.
+pub async fn watch_config(
+    path: PathBuf,
+    load: ConfigLoader,
+    mut config: Config,
+    filters: watch::Sender<Option<SubscriptionFilters>>,
+    ws_server: Arc<WsServer>,
+) {
+    let mut modified = modified_time(&path);
+    let mut triggers = Triggers::new(config.server.config_reload_interval_ms);
+    loop {
+        let hangup = triggers.next().await;
+        let now = modified_time(&path);
+        if !hangup && now == modified {
+            continue;
+        }
+        modified = now;
+
+        let new = match load(&path) {
+            Ok(new) => new,
+            Err(e) => {
+                error!("Failed to reload config from {}, keeping the old one: {:#}", path.display(), e);
+                continue;
+            }
+        };
+        if streams(&new) == streams(&config) {
+            info!("🔄 Reloaded {}, subscription unchanged", path.display());
+        } else {
+            info!("🔄 Reloaded {}, updating the subscription", path.display());
+            let event_types = new.get_enabled_event_types();
+            ws_server.set_streams(
+                new.get_enabled_protocol_names(),
+                event_types.iter().map(|t| event_types::event_type_name(*t)).collect(),
+            );
+            filters.send_replace(subscription_filters(&new));
+        }
+        if needs_restart(&config, &new) {
+            warn!("⚠️  Only [events] and [protocols] are reloaded, restart to apply the other changes");
+        }
+        config = new;
+    }
+}
+
+/// What the gRPC subscription depends on
+fn streams(config: &Config) -> (Vec<String>, Vec<&'static str>) {
+    let event_types = config.get_enabled_event_types();
+    (
+        config.get_enabled_program_ids(),
+        event_types.iter().map(|t| event_types::event_type_name(*t)).collect(),
+    )
+}
+
+/// Whether anything outside `[events]` and `[protocols]` changed
+fn needs_restart(old: &Config, new: &Config) -> bool {
+    let rest = |config: &Config| {
+        let mut table = toml::Table::try_from(config).unwrap_or_default();
+        table.remove("events");
+        table.remove("protocols");
+        table
+    };
+    rest(old) != rest(new)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The poll interval and SIGHUP, whichever comes first
+struct Triggers {
+    interval: Option<tokio::time::Interval>,
+    #[cfg(unix)]
+    hangup: Option<tokio::signal::unix::Signal>,
+}
+
+impl Triggers {
+    fn new(interval_ms: u64) -> Self {
+        let interval = (interval_ms > 0).then(|| {
+            let mut interval = tokio::time::interval(Duration::from_millis(interval_ms.max(100)));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval.reset();
+            interval
+        });
+
+        #[cfg(unix)]
+        let hangup = {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::hangup()) {
+                Ok(hangup) => Some(hangup),
+                Err(e) => {
+                    warn!("⚠️  Failed to listen for SIGHUP: {}", e);
+                    None
+                }
+            }
+        };
+
+        Self {
+            interval,
+            #[cfg(unix)]
+            hangup,
+        }
+    }
+
+    /// `true` for SIGHUP, which reloads even when the file looks unchanged
+    async fn next(&mut self) -> bool {
+        let tick = async {
+            match &mut self.interval {
+                Some(interval) => {
+                    interval.tick().await;
+                }
+                None => std::future::pending::<()>().await,
+            }
+        };
+
+        #[cfg(unix)]
+        let hangup = async {
+            let received = match &mut self.hangup {
+                Some(hangup) => hangup.recv().await.is_some(),
+                None => false,
+            };
+            if !received {
+                std::future::pending::<()>().await;
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tick => false,
+            _ = hangup => {
+                info!("📥 Received SIGHUP");
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EventsConfig;
+
+    #[test]
+    fn test_reload_diff() {
+        let config = Config::default();
+        assert!(subscription_filters(&config).is_some());
+
+        let mut new = config.clone();
+        new.events = EventsConfig::Patterns(vec!["RaydiumClmm*".to_string()]);
+        assert_ne!(streams(&new), streams(&config));
+        assert!(!needs_restart(&config, &new));
+
+        new.server.port += 1;
+        assert!(needs_restart(&config, &new));
+
+        new.events = EventsConfig::Patterns(Vec::new());
+        assert!(subscription_filters(&new).is_none());
+    }
+}
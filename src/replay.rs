@@ -0,0 +1,115 @@
+use crate::envelope::Header;
+use sol_parser_sdk::grpc::EventType;
+use sol_parser_sdk::DexEvent;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// An event kept for replay, with the envelope header it was first sent with
+pub struct Entry {
+    pub header: Header,
+    pub event_type: EventType,
+    pub event: DexEvent,
+    pub labels: Vec<String>,
+}
+
+/// The most recent events, bounded by count and optionally by age, so that a
+/// reconnecting client can `resume` from the last sequence number it saw.
+/// Entries are shared so a resume can take a snapshot without copying events.
+pub struct ReplayBuffer {
+    entries: VecDeque<Arc<Entry>>,
+    capacity: usize,
+    window_us: i64,
+    last_seq: u64,
+}
+
+impl ReplayBuffer {
+    /// `window_ms` of 0 keeps events until `capacity` pushes them out
+    pub fn new(capacity: usize, window_ms: u64) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: VecDeque::with_capacity(capacity.min(65536)),
+            capacity,
+            window_us: (window_ms as i64).saturating_mul(1000),
+            last_seq: 0,
+        }
+    }
+
+    /// Append an event; sequence numbers must be pushed in increasing order
+    pub fn push(&mut self, entry: Entry) {
+        let now_us = entry.header.proxy_recv_us;
+        self.last_seq = entry.header.seq;
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Arc::new(entry));
+
+        if self.window_us > 0 {
+            while self
+                .entries
+                .front()
+                .is_some_and(|oldest| now_us - oldest.header.proxy_recv_us > self.window_us)
+            {
+                self.entries.pop_front();
+            }
+        }
+    }
+
+    /// Whether every event from `from_seq` onwards is still held
+    pub fn covers(&self, from_seq: u64) -> bool {
+        match self.entries.front() {
+            Some(oldest) => oldest.header.seq <= from_seq,
+            None => from_seq > self.last_seq,
+        }
+    }
+
+    /// The held events with `from_seq <= seq <= through_seq`, oldest first
+    pub fn range(&self, from_seq: u64, through_seq: u64) -> impl Iterator<Item = &Arc<Entry>> {
+        let start = self.entries.partition_point(|entry| entry.header.seq < from_seq);
+        self.entries
+            .range(start..)
+            .take_while(move |entry| entry.header.seq <= through_seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::block_meta;
+
+    fn entry(seq: u64, proxy_recv_us: i64) -> Entry {
+        Entry {
+            header: Header {
+                seq,
+                event_type: "BlockMeta",
+                protocol: "solana",
+                proxy_recv_us,
+            },
+            event_type: EventType::BlockMeta,
+            event: block_meta(seq),
+            labels: Vec::new(),
+        }
+    }
+
+    fn seqs(buffer: &ReplayBuffer, from_seq: u64, through_seq: u64) -> Vec<u64> {
+        buffer.range(from_seq, through_seq).map(|e| e.header.seq).collect()
+    }
+
+    #[test]
+    fn test_capacity_and_window() {
+        let mut buffer = ReplayBuffer::new(3, 0);
+        for seq in 1..=5 {
+            buffer.push(entry(seq, 0));
+        }
+        assert_eq!(seqs(&buffer, 0, u64::MAX), vec![3, 4, 5]);
+        assert_eq!(seqs(&buffer, 4, 4), vec![4]);
+        assert!(buffer.covers(3));
+        assert!(!buffer.covers(2));
+        assert_eq!(buffer.last_seq, 5);
+
+        let mut buffer = ReplayBuffer::new(100, 1);
+        buffer.push(entry(1, 0));
+        buffer.push(entry(2, 500));
+        buffer.push(entry(3, 1_500));
+        assert_eq!(seqs(&buffer, 0, u64::MAX), vec![2, 3]);
+    }
+}
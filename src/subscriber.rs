@@ -0,0 +1,103 @@
+use crate::client_queue::{ClientQueue, Delivery, PushOutcome};
+use crate::event_types::EventTypeSet;
+use crate::filter::{ClientFilter, EventKeys, FilterSpec};
+use dashmap::DashMap;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use sol_parser_sdk::grpc::EventType;
+use sol_parser_sdk::DexEvent;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tracing::warn;
+
+/// What an in-process subscriber receives, in the terms a WebSocket client
+/// uses for `subscribe` and `set_filter`
+#[derive(Debug, Clone, Default)]
+pub struct EventSubscription {
+    /// Event type names such as `PumpFunTrade`; empty receives every type
+    /// the server streams
+    pub event_types: Vec<String>,
+    pub filter: FilterSpec,
+}
+
+/// An in-process subscriber as seen by the fan-out
+pub(crate) struct Subscriber {
+    pub queue: Arc<ClientQueue<DexEvent>>,
+    pub event_types: EventTypeSet,
+    pub filter: ClientFilter,
+}
+
+impl Subscriber {
+    #[inline]
+    pub fn wants(&self, event_type: EventType, keys: &EventKeys) -> bool {
+        self.event_types.contains(event_type) && (self.filter.is_empty() || self.filter.matches(keys))
+    }
+
+    pub fn push(&self, event: &DexEvent) -> PushOutcome {
+        self.queue.push(event.clone())
+    }
+}
+
+/// Parsed events matching an `EventSubscription`, delivered in broadcast
+/// order. The queue follows the server's `client_queue_capacity` and
+/// slow-consumer policy; `dropped` counts what a slow reader lost. The
+/// stream ends when the server shuts down or, under the `disconnect`
+/// policy, when the reader falls too far behind.
+pub struct EventStream {
+    events: BoxStream<'static, DexEvent>,
+    queue: Arc<ClientQueue<DexEvent>>,
+    id: usize,
+    subscribers: Arc<DashMap<usize, Subscriber>>,
+}
+
+impl EventStream {
+    pub(crate) fn new(id: usize, queue: Arc<ClientQueue<DexEvent>>, subscribers: Arc<DashMap<usize, Subscriber>>) -> Self {
+        let events = stream::unfold(queue.clone(), move |queue| async move {
+            loop {
+                match queue.pop().await {
+                    Delivery::Message(event) => return Some((event, queue)),
+                    Delivery::Lagged { dropped, .. } => {
+                        warn!("In-process subscriber {} lagged, dropped {} events", id, dropped)
+                    }
+                    Delivery::RateLimited { .. } => {}
+                    Delivery::Disconnect { lag } => {
+                        warn!("Closing in-process subscriber {} with {} queued events", id, lag);
+                        return None;
+                    }
+                    Delivery::Closed | Delivery::Drained => return None,
+                }
+            }
+        });
+        Self {
+            events: events.boxed(),
+            queue,
+            id,
+            subscribers,
+        }
+    }
+
+    /// The next event, or `None` once the stream has ended
+    pub async fn recv(&mut self) -> Option<DexEvent> {
+        self.events.next().await
+    }
+
+    /// Events dropped by the slow-consumer policy so far
+    pub fn dropped(&self) -> u64 {
+        self.queue.total_dropped()
+    }
+}
+
+impl Stream for EventStream {
+    type Item = DexEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DexEvent>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.queue.close();
+        self.subscribers.remove(&self.id);
+    }
+}
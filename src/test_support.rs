@@ -0,0 +1,30 @@
+use sol_parser_sdk::core::events::{BlockMetaEvent, EventMetadata};
+use sol_parser_sdk::DexEvent;
+use solana_sdk::signature::Signature;
+
+/// A `BlockMeta` event for `slot` with the rest of its metadata zeroed
+pub fn block_meta(slot: u64) -> DexEvent {
+    DexEvent::BlockMeta(BlockMetaEvent {
+        metadata: EventMetadata {
+            signature: Signature::default(),
+            slot,
+            tx_index: 0,
+            block_time_us: 0,
+            grpc_recv_us: 0,
+        },
+    })
+}
+
+/// A `BlockMeta` event whose metadata fields all differ, so encodings can
+/// be checked field by field
+pub fn signed_block_meta(signature: Signature, slot: u64) -> DexEvent {
+    DexEvent::BlockMeta(BlockMetaEvent {
+        metadata: EventMetadata {
+            signature,
+            slot,
+            tx_index: 1,
+            block_time_us: 2,
+            grpc_recv_us: 3,
+        },
+    })
+}
@@ -0,0 +1,101 @@
+use crate::config::TlsConfig;
+use anyhow::{Context, Result};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::TlsAcceptor;
+use tracing::{error, info};
+
+/// Terminates TLS with the configured certificate, picking up a renewed
+/// certificate or key when either file's modification time changes.
+/// Connections already open keep the certificate they were accepted with.
+pub struct CertReloader {
+    config: TlsConfig,
+    acceptor: RwLock<TlsAcceptor>,
+    modified: RwLock<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl CertReloader {
+    pub fn load(config: &TlsConfig) -> Result<Self> {
+        let modified = modified_times(config);
+        let acceptor = build_acceptor(config)?;
+        Ok(Self {
+            config: config.clone(),
+            acceptor: RwLock::new(acceptor),
+            modified: RwLock::new(modified),
+        })
+    }
+
+    /// The acceptor for the current certificate
+    pub fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap().clone()
+    }
+
+    /// Poll the certificate files every `reload_interval_ms`. A reload that
+    /// fails, e.g. while the files are half written, keeps the old certificate
+    /// and is retried on the next change.
+    pub async fn watch(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.reload_interval_ms.max(100)));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let modified = modified_times(&self.config);
+            if modified == *self.modified.read().unwrap() {
+                continue;
+            }
+            *self.modified.write().unwrap() = modified;
+
+            match build_acceptor(&self.config) {
+                Ok(acceptor) => {
+                    *self.acceptor.write().unwrap() = acceptor;
+                    info!("🔐 Reloaded TLS certificate from {}", self.config.cert_path);
+                }
+                Err(e) => error!("Failed to reload TLS certificate, keeping the old one: {:#}", e),
+            }
+        }
+    }
+}
+
+fn modified_times(config: &TlsConfig) -> (Option<SystemTime>, Option<SystemTime>) {
+    let modified = |path: &str| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    (modified(&config.cert_path), modified(&config.key_path))
+}
+
+fn build_acceptor(config: &TlsConfig) -> Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read TLS certificate {}", config.cert_path))?;
+    let key = PrivateKeyDer::from_pem_file(&config.key_path)
+        .with_context(|| format!("Failed to read TLS key {}", config.key_path))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Invalid TLS certificate or key")?;
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_load_reports_bad_files() {
+        let mut cert = tempfile::NamedTempFile::new().unwrap();
+        cert.write_all(b"not a certificate").unwrap();
+        let config = TlsConfig {
+            cert_path: cert.path().to_string_lossy().into_owned(),
+            key_path: "/nonexistent/key.pem".to_string(),
+            reload_interval_ms: 1000,
+        };
+
+        let message = format!("{:#}", CertReloader::load(&config).err().unwrap());
+        assert!(message.contains("TLS"), "{}", message);
+    }
+}
@@ -0,0 +1,510 @@
+use crate::config::{EndpointConfig, FeedMode, GrpcConfig, PipelineConfig, WaitStrategy};
+use crate::dedup::{Deduplicator, EmitOrder};
+use crate::geyser::GeyserStream;
+use crate::hooks::Hooks;
+use crate::pipeline;
+use crate::protocol::ServerMessage;
+use crate::ws_server::WsServer;
+use anyhow::Result;
+use sol_parser_sdk::grpc::{AccountFilter, EventTypeFilter, TransactionFilter};
+use sol_parser_sdk::DexEvent;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+/// Health of the gRPC subscriptions, shared with the WebSocket server
+#[derive(Debug, Default)]
+pub struct UpstreamStats {
+    connected_feeds: AtomicUsize,
+    reconnects: AtomicU64,
+    /// Events thrown away because a feed's event queue was full
+    ingest_dropped: AtomicU64,
+    /// When the last event was broadcast, 0 before the first one
+    last_event_us: AtomicI64,
+    /// `grpc.stall_timeout_ms` in µs, set when the upstream starts
+    stall_timeout_us: AtomicI64,
+}
+
+impl UpstreamStats {
+    /// Whether at least one feed is subscribed
+    pub fn is_connected(&self) -> bool {
+        self.connected_feeds.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn connected_feeds(&self) -> usize {
+        self.connected_feeds.load(Ordering::Relaxed)
+    }
+
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Events lost because the consumer fell behind the gRPC stream
+    pub fn ingest_dropped(&self) -> u64 {
+        self.ingest_dropped.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub(crate) fn record_ingest_dropped(&self) {
+        self.ingest_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn record_event(&self, at_us: i64) {
+        self.last_event_us.store(at_us, Ordering::Relaxed);
+    }
+
+    /// Microseconds since the last broadcast event, if there was one
+    pub fn last_event_age_us(&self) -> Option<i64> {
+        match self.last_event_us.load(Ordering::Relaxed) {
+            0 => None,
+            at_us => Some((now_us() - at_us).max(0)),
+        }
+    }
+
+    /// Subscribed and delivered an event within the stall timeout (any event
+    /// when stall detection is off), i.e. the subscription is neither down
+    /// nor about to be declared stalled
+    pub fn is_ready(&self) -> bool {
+        let stall_timeout_us = self.stall_timeout_us.load(Ordering::Relaxed);
+        self.is_connected()
+            && self
+                .last_event_age_us()
+                .is_some_and(|age| stall_timeout_us == 0 || age <= stall_timeout_us)
+    }
+}
+
+/// The filters a subscription is built from
+#[derive(Clone)]
+pub struct SubscriptionFilters {
+    pub transaction: TransactionFilter,
+    pub account: AccountFilter,
+    pub event_types: EventTypeFilter,
+}
+
+/// One live subscription, as seen by the consumer draining it
+pub struct Feed {
+    stop: AtomicBool,
+    /// Signalled when the stream queues an event and when the feed stops
+    wakeup: Notify,
+    last_event_us: AtomicI64,
+    dedup: Option<Arc<Deduplicator>>,
+    /// Only touched by the feed's consumer, so never contended
+    order: Mutex<EmitOrder>,
+    hooks: Hooks,
+}
+
+impl Feed {
+    pub(crate) fn new(dedup: Option<Arc<Deduplicator>>, hooks: Hooks) -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            wakeup: Notify::new(),
+            last_event_us: AtomicI64::new(now_us()),
+            dedup,
+            order: Mutex::default(),
+            hooks,
+        }
+    }
+
+    /// The event hooks the consumer runs on accepted events
+    #[inline]
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    /// Make the consumer draining this feed exit
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wakeup.notify_one();
+    }
+
+    /// Wake a consumer waiting in `woken`; a wakeup with no consumer waiting
+    /// is kept for the next call
+    #[inline]
+    pub fn wake(&self) {
+        self.wakeup.notify_one();
+    }
+
+    /// Wait for `wake` or `stop`
+    pub async fn woken(&self) {
+        self.wakeup.notified().await;
+    }
+
+    #[inline]
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Record that the feed is alive and decide whether to forward the event;
+    /// `false` means another feed already delivered it
+    #[inline]
+    pub fn accept(&self, event: &DexEvent) -> bool {
+        self.last_event_us.store(now_us(), Ordering::Relaxed);
+        self.dedup
+            .as_ref()
+            .is_none_or(|dedup| dedup.first_seen(event, &mut self.order.lock().unwrap()))
+    }
+}
+
+/// Stops a feed's consumer when the supervisor moves on from the feed or is
+/// dropped mid-watch, as `run` is on shutdown
+struct StopOnDrop(Arc<Feed>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+/// Run the gRPC side of the proxy according to `grpc.mode`; never returns
+/// unless every feed task ends. Dropping the future stops the consumers.
+/// Every feed resubscribes when `filters` changes, and stays down while it
+/// is `None`.
+pub async fn run(
+    grpc: GrpcConfig,
+    mut pipeline: PipelineConfig,
+    filters: watch::Receiver<Option<SubscriptionFilters>>,
+    ws_server: Arc<WsServer>,
+    hooks: Hooks,
+) -> Result<()> {
+    let endpoints = grpc.endpoints();
+    ws_server.upstream().stall_timeout_us.store(
+        (grpc.stall_timeout_ms as i64).saturating_mul(1000),
+        Ordering::Relaxed,
+    );
+
+    if grpc.mode == FeedMode::Failover || endpoints.len() == 1 {
+        return Supervisor::new(grpc, pipeline, filters, ws_server, hooks, endpoints, None)
+            .run()
+            .await;
+    }
+
+    info!("🏁 Racing {} gRPC endpoints", endpoints.len());
+    // Every feed has its own consumer
+    if let Some(core) = pipeline.cpu_core.take() {
+        warn!(
+            "⚠️  cpu_core = {} is ignored in race mode, pinning {} consumers to one core would serialize them",
+            core,
+            endpoints.len()
+        );
+    }
+    if pipeline.wait_strategy == WaitStrategy::BusySpin {
+        warn!(
+            "⚠️  busy_spin in race mode runs {} consumer threads, each keeping a core at 100%",
+            endpoints.len()
+        );
+    }
+    let dedup = Arc::new(Deduplicator::new(grpc.dedup_window));
+    let mut feeds = JoinSet::new();
+    for endpoint in endpoints {
+        let supervisor = Supervisor::new(
+            grpc.clone(),
+            pipeline.clone(),
+            filters.clone(),
+            ws_server.clone(),
+            hooks.clone(),
+            vec![endpoint],
+            Some(dedup.clone()),
+        );
+        feeds.spawn(supervisor.run());
+    }
+
+    while let Some(result) = feeds.join_next().await {
+        result??;
+    }
+    Ok(())
+}
+
+/// Keeps the Yellowstone subscription alive.
+///
+/// A feed counts as connected once it delivers its first event. A feed
+/// whose stream fails or ends is rebuilt with jittered exponential backoff,
+/// moving to the next of its endpoints each time, and WebSocket clients are
+/// told when the upstream goes down and up. With `stall_timeout_ms` set, a
+/// feed that is silent that long is replaced too.
+///
+/// When a config reload changes the filters, the feed is replaced right
+/// away, without backoff and without telling clients the upstream went
+/// down. A replaced feed's stream is aborted, closing its gRPC stream.
+pub struct Supervisor {
+    grpc: GrpcConfig,
+    pipeline: PipelineConfig,
+    filters: watch::Receiver<Option<SubscriptionFilters>>,
+    ws_server: Arc<WsServer>,
+    hooks: Hooks,
+    endpoints: Vec<EndpointConfig>,
+    dedup: Option<Arc<Deduplicator>>,
+}
+
+impl Supervisor {
+    fn new(
+        grpc: GrpcConfig,
+        pipeline: PipelineConfig,
+        filters: watch::Receiver<Option<SubscriptionFilters>>,
+        ws_server: Arc<WsServer>,
+        hooks: Hooks,
+        endpoints: Vec<EndpointConfig>,
+        dedup: Option<Arc<Deduplicator>>,
+    ) -> Self {
+        Self { grpc, pipeline, filters, ws_server, hooks, endpoints, dedup }
+    }
+
+    async fn run(mut self) -> Result<()> {
+        let stats = self.ws_server.upstream().clone();
+        let stall_timeout = Duration::from_millis(self.grpc.stall_timeout_ms);
+        let mut attempt = 0u32;
+        let mut index = 0usize;
+        let mut reloaded = false;
+
+        loop {
+            let Some(filters) = self.filters.borrow_and_update().clone() else {
+                // Nothing to subscribe to until a reload enables events
+                if self.filters.changed().await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+                continue;
+            };
+            let endpoint = &self.endpoints[index % self.endpoints.len()];
+            let feed = Arc::new(Feed::new(self.dedup.clone(), self.hooks.clone()));
+            match GeyserStream::subscribe(&self.grpc, endpoint, &filters, feed.clone(), stats.clone()).await {
+                Ok((stream, queue)) => {
+                    let _stop = StopOnDrop(feed.clone());
+                    pipeline::spawn_consumer(
+                        queue,
+                        self.ws_server.clone(),
+                        &self.pipeline,
+                        feed.clone(),
+                    );
+
+                    let subscribed_at = now_us();
+                    let first_event = watch(&feed, &stream, &self.filters, stall_timeout, || {
+                        feed.last_event_us.load(Ordering::Relaxed) > subscribed_at
+                    })
+                    .await;
+
+                    let (end, was_up) = match first_event {
+                        Err(end) => (end, false),
+                        Ok(()) => {
+                            info!("✅ Subscription established: {}", endpoint.endpoint);
+                            // A subscription that delivers events is healthy,
+                            // so start the backoff over
+                            attempt = 0;
+                            let was_connected = stats.connected_feeds.fetch_add(1, Ordering::Relaxed) > 0;
+                            if !was_connected && stats.reconnects() > 0 && !reloaded {
+                                self.ws_server.notify_all(&ServerMessage::Upstream {
+                                    connected: true,
+                                    reconnects: stats.reconnects(),
+                                });
+                            }
+                            let end = watch(&feed, &stream, &self.filters, stall_timeout, || false).await;
+                            (end.unwrap_err(), true)
+                        }
+                    };
+
+                    feed.stop();
+                    drop(stream);
+                    if end == FeedEnd::Reloaded {
+                        info!("🔁 Subscription filters changed, resubscribing to {}", endpoint.endpoint);
+                        if was_up {
+                            stats.connected_feeds.fetch_sub(1, Ordering::Relaxed);
+                        }
+                        reloaded = true;
+                        continue;
+                    }
+                    reloaded = false;
+                    let reconnects = stats.reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+                    if was_up && stats.connected_feeds.fetch_sub(1, Ordering::Relaxed) == 1 {
+                        self.ws_server.notify_all(&ServerMessage::Upstream {
+                            connected: false,
+                            reconnects,
+                        });
+                    }
+                    match end {
+                        FeedEnd::Closed => warn!(
+                            "⚠️  Stream from {} failed or ended (reconnect #{})",
+                            endpoint.endpoint, reconnects
+                        ),
+                        FeedEnd::Stalled => warn!(
+                            "⚠️  No events from {} for {:?}, resubscribing (reconnect #{})",
+                            endpoint.endpoint, stall_timeout, reconnects
+                        ),
+                        FeedEnd::Reloaded => unreachable!(),
+                    }
+                }
+                Err(e) => {
+                    reloaded = false;
+                    let reconnects = stats.reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+                    error!("❌ {}: {:#} (reconnect #{})", endpoint.endpoint, e, reconnects);
+                }
+            }
+
+            index = index.wrapping_add(1);
+            let delay = backoff_delay(&self.grpc, attempt);
+            attempt = attempt.saturating_add(1);
+            info!(
+                "🔄 Connecting to {} in {:?}",
+                self.endpoints[index % self.endpoints.len()].endpoint,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Why a feed is given up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeedEnd {
+    /// The stream task exited: the stream failed or ended
+    Closed,
+    /// Silent for `stall_timeout_ms`
+    Stalled,
+    /// A config reload changed the subscription filters
+    Reloaded,
+}
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Poll until `done` holds or the feed ends.
+/// A zero `stall_timeout` disables stall detection.
+async fn watch(
+    feed: &Feed,
+    stream: &GeyserStream,
+    filters: &watch::Receiver<Option<SubscriptionFilters>>,
+    stall_timeout: Duration,
+    mut done: impl FnMut() -> bool,
+) -> Result<(), FeedEnd> {
+    let stall_timeout_us = stall_timeout.as_micros() as i64;
+
+    loop {
+        if done() {
+            return Ok(());
+        }
+        if filters.has_changed().unwrap_or(false) {
+            return Err(FeedEnd::Reloaded);
+        }
+        if stream.is_closed() {
+            return Err(FeedEnd::Closed);
+        }
+        if stall_timeout_us > 0 && now_us() - feed.last_event_us.load(Ordering::Relaxed) > stall_timeout_us {
+            return Err(FeedEnd::Stalled);
+        }
+        tokio::time::sleep(WATCH_INTERVAL).await;
+    }
+}
+
+/// Exponential backoff with equal jitter: half the delay is fixed, the other
+/// half random, so many proxies don't reconnect in lockstep
+fn backoff_delay(grpc: &GrpcConfig, attempt: u32) -> Duration {
+    let base = grpc
+        .reconnect_initial_delay_ms
+        .saturating_mul(1u64 << attempt.min(20))
+        .min(grpc.reconnect_max_delay_ms);
+    let half = base / 2;
+    Duration::from_millis(half + fastrand::u64(0..=half))
+}
+
+pub fn now_us() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let mut grpc = Config::default().grpc;
+        grpc.reconnect_initial_delay_ms = 100;
+        grpc.reconnect_max_delay_ms = 1000;
+
+        let first = backoff_delay(&grpc, 0);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+        for attempt in [4, 10, 40] {
+            let delay = backoff_delay(&grpc, attempt);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn test_race_feeds_forward_each_event_once() {
+        use crate::test_support::signed_block_meta;
+        use solana_sdk::signature::Signature;
+
+        // A transaction whose three events are told apart by block time
+        let event = |n| match signed_block_meta(Signature::from([7; 64]), 9) {
+            DexEvent::BlockMeta(mut e) => {
+                e.metadata.block_time_us = n;
+                DexEvent::BlockMeta(e)
+            }
+            _ => unreachable!(),
+        };
+        let dedup = Arc::new(Deduplicator::new(64));
+        let a = Feed::new(Some(dedup.clone()), Hooks::default());
+        let b = Feed::new(Some(dedup.clone()), Hooks::default());
+
+        // Both feeds emit the events in order, interleaved with each other;
+        // whichever copy arrives first is the one forwarded
+        let arrivals = [(&a, 0), (&b, 0), (&b, 1), (&a, 1), (&a, 2), (&b, 2)];
+        let forwarded: Vec<_> = arrivals
+            .into_iter()
+            .filter(|(feed, n)| feed.accept(&event(*n)))
+            .map(|(feed, n)| (std::ptr::eq(feed, &a), n))
+            .collect();
+        assert_eq!(forwarded, [(true, 0), (false, 1), (true, 2)]);
+
+        // Copies are matched by position, so if one feed emitted the
+        // transaction in another order, event 0 would go out twice and
+        // event 1 never
+        let dedup = Arc::new(Deduplicator::new(64));
+        let a = Feed::new(Some(dedup.clone()), Hooks::default());
+        let b = Feed::new(Some(dedup.clone()), Hooks::default());
+        assert!(a.accept(&event(0)));
+        assert!(!b.accept(&event(1)));
+        assert!(b.accept(&event(0)));
+        assert!(!a.accept(&event(1)));
+    }
+
+    #[tokio::test]
+    async fn test_watch_detects_closed_stream() {
+        let feed = Feed::new(None, Hooks::default());
+        let (ended_tx, ended) = tokio::sync::oneshot::channel::<()>();
+        let stream = GeyserStream::spawn(async move {
+            let _ = ended.await;
+        });
+
+        let (_filters_tx, filters) = watch::channel(None);
+        let watching = watch(&feed, &stream, &filters, Duration::ZERO, || false);
+        drop(ended_tx);
+        assert_eq!(watching.await, Err(FeedEnd::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_watch_detects_stall() {
+        let feed = Feed::new(None, Hooks::default());
+        let stream = GeyserStream::spawn(std::future::pending());
+
+        let (_filters_tx, filters) = watch::channel(None);
+        let end = watch(&feed, &stream, &filters, Duration::from_millis(1), || false).await;
+        assert_eq!(end, Err(FeedEnd::Stalled));
+        assert_eq!(watch(&feed, &stream, &filters, Duration::ZERO, || true).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_watch_detects_reload() {
+        let feed = Feed::new(None, Hooks::default());
+        let stream = GeyserStream::spawn(std::future::pending());
+
+        let (filters_tx, filters) = watch::channel(None);
+        filters_tx.send_replace(None);
+        let end = watch(&feed, &stream, &filters, Duration::ZERO, || false).await;
+        assert_eq!(end, Err(FeedEnd::Reloaded));
+    }
+}
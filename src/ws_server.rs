@@ -1,38 +1,368 @@
+use crate::auth::{ApiKey, Auth, Rejection};
+use crate::client_queue::{ClientQueue, Delivery, PushOutcome};
+use crate::config::{AuthConfig, ServerConfig, TlsConfig};
+use crate::envelope::Header;
+use crate::event_types::{event_metadata, event_type_name, event_type_of, parse_event_type, protocol_name, EventTypeSet};
+use crate::filter::{event_keys, ClientFilter, EventKeys};
+use crate::http::{self, Head, Prefixed};
+use crate::metrics::{Metrics, ServerStats, Snapshot};
+use crate::normalize::{normalize_trade, NormalizedEvent, NORMALIZED_TRADE};
+use crate::output::{self, Encoding, OutputFormat};
+use crate::protocol::{ClientRequest, ServerMessage};
+use crate::replay::{Entry, ReplayBuffer};
+use crate::subscriber::{EventStream, EventSubscription, Subscriber};
+use crate::tls::CertReloader;
+use crate::upstream::{now_us, UpstreamStats};
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
-use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc;
-use tokio_tungstenite::{accept_async, tungstenite::Message};
-use tracing::{error, info, warn};
+use serde::Serialize;
+use serde_json::json;
+use sol_parser_sdk::grpc::EventType;
+use sol_parser_sdk::DexEvent;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::header::{SEC_WEBSOCKET_PROTOCOL, WWW_AUTHENTICATE};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::accept_hdr_async;
+use tracing::{debug, error, info, warn};
 
 type ClientId = usize;
 
+/// How often `shutdown` checks whether every client has drained
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Clone)]
+struct Client {
+    queue: Arc<ClientQueue<Outgoing>>,
+    subscription: Subscription,
+    encoding: Encoding,
+    format: OutputFormat,
+    /// Last event sequence number broadcast before this client registered
+    joined_seq: u64,
+    /// The API key the client connected with, when auth is enabled
+    key: Option<Arc<ApiKey>>,
+}
+
+/// A frame queued for a client, with when its event was received from
+/// gRPC so the send latency can be measured once it is written
+struct Outgoing {
+    frame: Message,
+    grpc_recv_us: Option<i64>,
+}
+
+impl From<Message> for Outgoing {
+    fn from(frame: Message) -> Self {
+        Self { frame, grpc_recv_us: None }
+    }
+}
+
+impl Client {
+    /// Whether the client's API key allows one more event this second
+    #[inline]
+    fn within_rate(&self, metrics: &Metrics) -> bool {
+        let allowed = self.key.as_ref().is_none_or(|key| key.allow_message());
+        if !allowed {
+            self.queue.record_rate_limited();
+            metrics.message_rate_limited();
+        }
+        allowed
+    }
+}
+
+/// The event types a client receives.
+///
+/// A new client receives every raw event the server streams, limited to what
+/// its API key allows, until it sends its first `subscribe` or `unsubscribe`,
+/// after which only the explicit set applies. The normalized trade stream is
+/// only sent to clients that subscribe to it.
+#[derive(Clone)]
+struct Subscription {
+    event_types: EventTypeSet,
+    /// Event types the client's API key entitles it to
+    allowed: EventTypeSet,
+    trades: bool,
+    explicit: bool,
+    filter: ClientFilter,
+}
+
+impl Subscription {
+    fn new(allowed: EventTypeSet) -> Self {
+        Self {
+            event_types: allowed,
+            allowed,
+            trades: false,
+            explicit: false,
+            filter: ClientFilter::default(),
+        }
+    }
+
+    fn subscribe(&mut self, streams: &Streams) {
+        if !self.explicit {
+            self.event_types = EventTypeSet::empty();
+            self.explicit = true;
+        }
+        for event_type in &streams.event_types {
+            self.event_types.insert(*event_type);
+        }
+        self.trades |= streams.trades;
+    }
+
+    /// Whether a `NormalizedTrade` built from a swap of this type is wanted
+    #[inline]
+    fn wants_trade(&self, source: EventType) -> bool {
+        self.trades && self.allowed.contains(source)
+    }
+
+    #[inline]
+    fn matches_filter(&self, keys: &EventKeys) -> bool {
+        self.filter.is_empty() || self.filter.matches(keys)
+    }
+
+    fn unsubscribe(&mut self, streams: &Streams) {
+        self.explicit = true;
+        for event_type in &streams.event_types {
+            self.event_types.remove(*event_type);
+        }
+        if streams.trades {
+            self.trades = false;
+        }
+    }
+
+    fn names(&self) -> Vec<&'static str> {
+        let mut names = self.event_types.names();
+        if self.trades {
+            names.push(NORMALIZED_TRADE);
+        }
+        names
+    }
+}
+
+/// Stream names from a `subscribe` or `unsubscribe` request
+struct Streams {
+    event_types: Vec<EventType>,
+    trades: bool,
+}
+
+/// The ordering point of the fan-out. It is held while an event gets its
+/// sequence number and is queued for every client, so each client sees `seq`
+/// increase even when several consumers broadcast (`race` mode), and a client
+/// registering or resuming sees each event either live or replayed.
+/// Lock it before any client entry.
+struct Sequencer {
+    /// Sequence number of the last event taken off the parser queue
+    last_seq: u64,
+    replay: Option<ReplayBuffer>,
+}
+
+/// Where the server is in its shutdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Running,
+    /// No new connections; clients are sent what is queued and then closed
+    Draining,
+    /// The drain deadline passed; connections still open are dropped
+    Stopped,
+}
+
+/// Resolves once the server reaches `phase`, or never if the server is gone
+async fn reached(mut receiver: watch::Receiver<Phase>, phase: Phase) {
+    if receiver.wait_for(|current| *current >= phase).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// What a connection task shares with the server
+#[derive(Clone)]
+struct Context {
+    clients: Arc<DashMap<ClientId, Client>>,
+    upstream: Arc<UpstreamStats>,
+    sequencer: Arc<Mutex<Sequencer>>,
+    auth: Option<Arc<Auth>>,
+    info: Arc<ServerInfo>,
+    metrics: Arc<Metrics>,
+    normalize_trades: bool,
+    phase: watch::Receiver<Phase>,
+}
+
+/// What `/info` reports besides live counters
+struct ServerInfo {
+    started_at: Instant,
+    /// Protocols and event types, replaced when a config reload changes them
+    streams: RwLock<(Vec<&'static str>, Vec<&'static str>)>,
+}
+
+/// WebSocket fan-out server: accepts clients and delivers each event to the
+/// clients whose subscription matches it
 pub struct WsServer {
-    clients: Arc<DashMap<ClientId, mpsc::UnboundedSender<String>>>,
+    clients: Arc<DashMap<ClientId, Client>>,
+    /// In-process subscribers, numbered from `next_client_id` too
+    subscribers: Arc<DashMap<ClientId, Subscriber>>,
     next_client_id: Arc<std::sync::atomic::AtomicUsize>,
+    config: ServerConfig,
+    upstream: Arc<UpstreamStats>,
+    sequencer: Arc<Mutex<Sequencer>>,
+    tls: Option<Arc<CertReloader>>,
+    auth: Option<Arc<Auth>>,
+    info: Arc<ServerInfo>,
+    metrics: Arc<Metrics>,
+    phase: watch::Sender<Phase>,
 }
 
 impl WsServer {
-    pub fn new() -> Self {
+    pub fn new(config: &ServerConfig) -> Self {
         Self {
             clients: Arc::new(DashMap::new()),
+            subscribers: Arc::new(DashMap::new()),
             next_client_id: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            config: config.clone(),
+            upstream: Arc::new(UpstreamStats::default()),
+            sequencer: Arc::new(Mutex::new(Sequencer {
+                last_seq: 0,
+                replay: (config.replay_capacity > 0)
+                    .then(|| ReplayBuffer::new(config.replay_capacity, config.replay_window_ms)),
+            })),
+            tls: None,
+            auth: None,
+            info: Arc::new(ServerInfo {
+                started_at: Instant::now(),
+                streams: RwLock::default(),
+            }),
+            metrics: Arc::new(Metrics::default()),
+            phase: watch::Sender::new(Phase::Running),
+        }
+    }
+
+    /// The protocols and event types the upstream subscribes to, as listed by `/info`
+    pub fn with_streams(self, protocols: Vec<&'static str>, event_types: Vec<&'static str>) -> Self {
+        self.set_streams(protocols, event_types);
+        self
+    }
+
+    /// Replace what `/info` lists after the subscription changed
+    pub fn set_streams(&self, protocols: Vec<&'static str>, event_types: Vec<&'static str>) {
+        *self.info.streams.write().unwrap() = (protocols, event_types);
+    }
+
+    /// Serve `wss://` with the given certificate, which is reloaded whenever
+    /// its files change while `run` is accepting connections
+    pub fn with_tls(mut self, tls: &TlsConfig) -> anyhow::Result<Self> {
+        self.tls = Some(Arc::new(CertReloader::load(tls)?));
+        Ok(self)
+    }
+
+    /// Require one of the configured API keys during the WebSocket upgrade
+    /// and limit each client to its key's entitlements
+    pub fn with_auth(mut self, auth: &AuthConfig) -> anyhow::Result<Self> {
+        self.auth = Some(Arc::new(Auth::load(auth)?));
+        Ok(self)
+    }
+
+    /// Health of the gRPC subscription feeding this server
+    pub fn upstream(&self) -> &Arc<UpstreamStats> {
+        &self.upstream
+    }
+
+    /// Receive the parsed events matching `subscription` in this process,
+    /// without going through a WebSocket
+    pub fn subscribe(&self, subscription: &EventSubscription) -> anyhow::Result<EventStream> {
+        let event_types = if subscription.event_types.is_empty() {
+            EventTypeSet::all()
+        } else {
+            let streams = parse_streams(&subscription.event_types).map_err(anyhow::Error::msg)?;
+            if streams.trades {
+                anyhow::bail!("{} is only available over WebSocket", NORMALIZED_TRADE);
+            }
+            streams.event_types.into_iter().collect()
+        };
+        let filter = ClientFilter::from_spec(&subscription.filter).map_err(anyhow::Error::msg)?;
+
+        let id = self
+            .next_client_id
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let queue = Arc::new(ClientQueue::new(
+            self.config.client_queue_capacity,
+            self.config.lag_threshold(),
+            self.config.slow_consumer_policy,
+        ));
+        if *self.phase.borrow() != Phase::Running {
+            queue.drain();
         }
+        self.subscribers.insert(
+            id,
+            Subscriber {
+                queue: queue.clone(),
+                event_types,
+                filter,
+            },
+        );
+        info!("In-process subscriber {} registered", id);
+        Ok(EventStream::new(id, queue, self.subscribers.clone()))
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// The counters `/metrics` exports, as plain numbers
+    pub fn stats(&self) -> ServerStats {
+        self.metrics.stats(self.clients.len(), &self.upstream)
     }
 
     pub async fn run(&self, listener: TcpListener) {
+        // Aborted when `run` stops
+        let mut background = JoinSet::new();
+        if let Some(tls) = &self.tls {
+            background.spawn(tls.clone().watch());
+        }
+
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = reached(self.phase.subscribe(), Phase::Draining) => break,
+            };
+            match accepted {
                 Ok((stream, addr)) => {
-                    info!("New client connected from: {}", addr);
                     let client_id = self
                         .next_client_id
                         .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                    let clients = self.clients.clone();
+                    let context = self.context();
+                    let queue = Arc::new(ClientQueue::new(
+                        self.config.client_queue_capacity,
+                        self.config.lag_threshold(),
+                        self.config.slow_consumer_policy,
+                    ));
+                    let tls = self.tls.as_ref().map(|tls| tls.acceptor());
+                    // Shared by the TLS handshake and reading the request
+                    let deadline = match self.config.handshake_timeout_ms {
+                        0 => None,
+                        ms => Some(tokio::time::Instant::now() + Duration::from_millis(ms)),
+                    };
 
                     tokio::spawn(async move {
-                        if let Err(e) = handle_connection(stream, client_id, clients).await {
+                        let result = match tls {
+                            Some(acceptor) => match within(deadline, acceptor.accept(stream)).await {
+                                Some(Ok(stream)) => serve(stream, addr, client_id, queue, context, deadline).await,
+                                Some(Err(e)) => {
+                                    warn!("TLS handshake with {} failed: {}", addr, e);
+                                    return;
+                                }
+                                None => {
+                                    debug!("TLS handshake with {} timed out", addr);
+                                    return;
+                                }
+                            },
+                            None => serve(stream, addr, client_id, queue, context, deadline).await,
+                        };
+                        if let Err(e) = result {
                             error!("Error handling connection {}: {}", client_id, e);
                         }
                     });
@@ -42,17 +372,158 @@ impl WsServer {
                 }
             }
         }
+        info!("🛑 WebSocket server stopped accepting connections");
     }
 
-    pub async fn broadcast(&self, message: &str) {
+    /// Stop accepting connections and close every client with `1001 Going
+    /// Away` once it has been sent what is already queued for it. Clients
+    /// still draining after `drain_timeout` are disconnected without a close
+    /// frame. Returns when every connection is gone.
+    pub async fn shutdown(&self, drain_timeout: Duration) {
+        self.phase.send_replace(Phase::Draining);
+        for entry in self.clients.iter() {
+            entry.value().queue.drain();
+        }
+        for entry in self.subscribers.iter() {
+            entry.value().queue.drain();
+        }
+        info!("⏳ Draining {} clients", self.clients.len());
+
+        if tokio::time::timeout(drain_timeout, self.closed()).await.is_err() {
+            warn!(
+                "⚠️  {} clients still draining after {:?}, dropping their connections",
+                self.clients.len(),
+                drain_timeout
+            );
+        }
+        self.phase.send_replace(Phase::Stopped);
+        self.closed().await;
+    }
+
+    /// Wait until every client connection has ended
+    async fn closed(&self) {
+        while !self.clients.is_empty() {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    fn context(&self) -> Context {
+        Context {
+            clients: self.clients.clone(),
+            upstream: self.upstream.clone(),
+            sequencer: self.sequencer.clone(),
+            auth: self.auth.clone(),
+            info: self.info.clone(),
+            metrics: self.metrics.clone(),
+            normalize_trades: self.config.normalize_trades,
+            phase: self.phase.subscribe(),
+        }
+    }
+
+    /// Serialize an event once per output format in use and queue it for
+    /// every client whose subscription and account filter match it; swaps are
+    /// also sent to `NormalizedTrade` subscribers when `normalize_trades` is enabled
+    pub fn broadcast_event(&self, event: &DexEvent) {
+        self.broadcast_labeled_event(event, &[]);
+    }
+
+    /// `broadcast_event` with labels for the envelope, as event hooks attach them
+    pub fn broadcast_labeled_event(&self, event: &DexEvent, labels: &[String]) {
+        let Some(event_type) = event_type_of(event) else {
+            debug!("Skipping event without an event type");
+            return;
+        };
+
+        // Held until the event is queued for every client
+        let mut sequencer = self.sequencer.lock().unwrap();
+        sequencer.last_seq += 1;
+        let header = Header {
+            seq: sequencer.last_seq,
+            event_type: event_type_name(event_type),
+            protocol: protocol_name(event_type),
+            proxy_recv_us: now_us(),
+        };
+        self.upstream.record_event(header.proxy_recv_us);
+        self.metrics.event_received(event_type);
+        if let Some(replay) = sequencer.replay.as_mut() {
+            replay.push(Entry {
+                header,
+                event_type,
+                event: event.clone(),
+                labels: labels.to_vec(),
+            });
+        }
+        let keys = event_keys(event);
+        for subscriber in self.subscribers.iter() {
+            if subscriber.wants(event_type, &keys) && subscriber.push(event) == PushOutcome::Dropped {
+                self.metrics.message_dropped();
+            }
+        }
+        let grpc_recv_us = event_metadata(event).map(|metadata| metadata.grpc_recv_us);
+        let mut messages =
+            self.broadcast(event, header, labels, &keys, grpc_recv_us, |s| s.event_types.contains(event_type));
+
+        if self.config.normalize_trades {
+            if let Some(trade) = normalize_trade(event) {
+                let trade = NormalizedEvent::NormalizedTrade(trade);
+                let header = Header {
+                    event_type: NORMALIZED_TRADE,
+                    ..header
+                };
+                messages += self.broadcast(&trade, header, labels, &keys, grpc_recv_us, |s| s.wants_trade(event_type));
+            }
+        }
+
+        self.metrics.event_broadcast(messages, grpc_recv_us.map(|recv_us| now_us() - recv_us));
+    }
+
+    /// Send a server notice to every connected client regardless of subscription
+    pub fn notify_all(&self, message: &ServerMessage) {
+        let frame = Message::text(message.to_json());
+        for entry in self.clients.iter() {
+            entry.value().queue.push_control(frame.clone().into());
+        }
+    }
+
+    /// Queue `value` for the matching clients; returns how many queues it was offered to
+    fn broadcast<T: Serialize>(
+        &self,
+        value: &T,
+        header: Header,
+        labels: &[String],
+        keys: &EventKeys,
+        grpc_recv_us: Option<i64>,
+        wants: impl Fn(&Subscription) -> bool,
+    ) -> u64 {
+        let mut frames = Frames::new(value, header, labels, &self.metrics);
         let mut disconnected = Vec::new();
+        let mut offered = 0;
 
         for entry in self.clients.iter() {
             let client_id = *entry.key();
-            let sender = entry.value();
+            let client = entry.value();
+
+            if !wants(&client.subscription) || !client.subscription.matches_filter(keys) {
+                continue;
+            }
+            if !client.within_rate(&self.metrics) {
+                continue;
+            }
 
-            if sender.send(message.to_string()).is_err() {
-                disconnected.push(client_id);
+            let Some(frame) = frames.get(client.encoding, client.format) else {
+                continue;
+            };
+
+            match client.queue.push(Outgoing {
+                frame: frame.clone(),
+                grpc_recv_us,
+            }) {
+                PushOutcome::Queued => offered += 1,
+                PushOutcome::Dropped => {
+                    offered += 1;
+                    self.metrics.message_dropped();
+                }
+                PushOutcome::Closed => disconnected.push(client_id),
             }
         }
 
@@ -60,35 +531,329 @@ impl WsServer {
             self.clients.remove(&client_id);
             info!("Removed disconnected client: {}", client_id);
         }
+        offered
     }
 }
 
-async fn handle_connection(
-    stream: TcpStream,
+/// One value's frames, serialized lazily the first time a client with a
+/// given encoding and output format needs it.
+///
+/// `Message` payloads are backed by `Bytes`, so every client clone of a frame
+/// shares one allocation. Enveloped frames are stamped with `proxy_send_us`
+/// when they are encoded, not per client.
+struct Frames<'a, T> {
+    value: &'a T,
+    header: Header,
+    labels: &'a [String],
+    metrics: &'a Metrics,
+    frames: [Option<Option<Message>>; Encoding::COUNT * OutputFormat::COUNT],
+}
+
+impl<'a, T: Serialize> Frames<'a, T> {
+    fn new(value: &'a T, header: Header, labels: &'a [String], metrics: &'a Metrics) -> Self {
+        Self {
+            value,
+            header,
+            labels,
+            metrics,
+            frames: Default::default(),
+        }
+    }
+
+    fn get(&mut self, encoding: Encoding, format: OutputFormat) -> Option<&Message> {
+        // bincode ignores the key and integer formats, so those share one frame
+        let format = if encoding == Encoding::Bincode {
+            OutputFormat {
+                envelope: format.envelope,
+                ..OutputFormat::default()
+            }
+        } else {
+            format
+        };
+        let (value, header, labels, metrics) = (self.value, self.header, self.labels, self.metrics);
+        self.frames[encoding as usize * OutputFormat::COUNT + format.index()]
+            .get_or_insert_with(|| {
+                let frame = if format.envelope {
+                    encode(&header.wrap(value, labels, now_us()), encoding, format)
+                } else {
+                    encode(value, encoding, format)
+                };
+                frame
+                    .inspect_err(|e| {
+                        metrics.serialization_error(encoding);
+                        error!("Failed to encode event as {}: {}", encoding.name(), e)
+                    })
+                    .ok()
+            })
+            .as_ref()
+    }
+}
+
+fn encode<T: Serialize>(value: &T, encoding: Encoding, format: OutputFormat) -> anyhow::Result<Message> {
+    match encoding {
+        Encoding::Json => Ok(Message::text(output::to_json(value, format)?)),
+        _ => output::to_binary(value, encoding, format).map(Message::binary),
+    }
+}
+
+/// Hand WebSocket upgrades to `handle_connection` and answer the HTTP
+/// endpoints served on the same port
+async fn serve<S>(
+    mut stream: S,
+    addr: SocketAddr,
     client_id: ClientId,
-    clients: Arc<DashMap<ClientId, mpsc::UnboundedSender<String>>>,
-) -> anyhow::Result<()> {
-    let ws_stream = accept_async(stream).await?;
+    queue: Arc<ClientQueue<Outgoing>>,
+    context: Context,
+    deadline: Option<tokio::time::Instant>,
+) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let Some(head) = within(deadline, http::read_head(&mut stream)).await else {
+        debug!("{} sent no request in time, closing", addr);
+        return Ok(());
+    };
+    match head? {
+        Head::Upgrade(head) => {
+            info!("New client connected from: {}", addr);
+            handle_connection(Prefixed::new(head, stream), client_id, queue, context).await
+        }
+        Head::Request(request) => {
+            let (method, path) = (request.method().as_str(), request.uri().path());
+            debug!("HTTP {} {} from {}", method, path, addr);
+            let response = match &context.auth {
+                // The probes stay open; what the proxy streams and to how many clients does not
+                Some(auth) if KEYED_PATHS.contains(&path) && !auth.recognizes(&request) => {
+                    http::Response::json(StatusCode::UNAUTHORIZED, json!({ "error": "missing or invalid API key" }))
+                }
+                _ => http_response(&context, method, path),
+            };
+            http::respond(&mut stream, &response, method != "HEAD").await?;
+            Ok(())
+        }
+        Head::Invalid(status) => {
+            let response = http::Response::json(status, json!({ "error": status.canonical_reason() }));
+            http::respond(&mut stream, &response, true).await?;
+            Ok(())
+        }
+    }
+}
+
+/// `future`'s output, or `None` if `deadline` passed first
+async fn within<F: std::future::Future>(deadline: Option<tokio::time::Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// HTTP endpoints that need an API key when `[server.auth]` is set
+const KEYED_PATHS: [&str; 2] = ["/info", "/metrics"];
+
+/// `/healthz`: the process is up. `/readyz`: the gRPC subscription is up
+/// and recently delivered events. `/info`: what the server streams.
+/// `/metrics`: Prometheus metrics.
+fn http_response(context: &Context, method: &str, path: &str) -> http::Response {
+    if method != "GET" && method != "HEAD" {
+        return http::Response::json(StatusCode::METHOD_NOT_ALLOWED, json!({ "error": "method not allowed" }));
+    }
+    if path == "/metrics" {
+        let snapshot = Snapshot {
+            upstream: &context.upstream,
+            queue_depths: context
+                .clients
+                .iter()
+                .map(|entry| entry.value().queue.len())
+                .collect(),
+        };
+        return http::Response {
+            status: StatusCode::OK,
+            content_type: http::PROMETHEUS_TEXT,
+            body: context.metrics.render(&snapshot),
+        };
+    }
+
+    let upstream = &context.upstream;
+    let (status, body) = match path {
+        "/healthz" => (StatusCode::OK, json!({ "status": "ok" })),
+        "/readyz" => {
+            let ready = upstream.is_ready();
+            let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            let body = json!({
+                "ready": ready,
+                "connected_feeds": upstream.connected_feeds(),
+                "last_event_age_ms": upstream.last_event_age_us().map(|us| us / 1000),
+            });
+            (status, body)
+        }
+        "/info" => {
+            let info = &context.info;
+            let (protocols, event_types) = &*info.streams.read().unwrap();
+            let body = json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "uptime_secs": info.started_at.elapsed().as_secs(),
+                "clients": context.clients.len(),
+                "protocols": protocols,
+                "event_types": event_types,
+                "normalize_trades": context.normalize_trades,
+                "upstream": {
+                    "connected": upstream.is_connected(),
+                    "reconnects": upstream.reconnects(),
+                },
+            });
+            (StatusCode::OK, body)
+        }
+        _ => (StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+    };
+    http::Response::json(status, body)
+}
+
+async fn handle_connection<S>(
+    stream: S,
+    client_id: ClientId,
+    queue: Arc<ClientQueue<Outgoing>>,
+    context: Context,
+) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut encoding = Encoding::default();
+    let mut format = OutputFormat::default();
+    let mut grant = None;
+    let mut resume_from = None;
+    // The handshake callback's error type is tungstenite's, not ours
+    #[allow(clippy::result_large_err)]
+    let handshake = accept_hdr_async(stream, |request: &Request, mut response: Response| {
+        if let Some(auth) = &context.auth {
+            grant = Some(auth.admit(request).map_err(|rejection| reject(client_id, rejection))?);
+        }
+
+        let query = request.uri().query().unwrap_or_default();
+        format = OutputFormat::from_query(query).map_err(bad_request)?;
+
+        // A `?encoding=` parameter wins over the offered subprotocols
+        let subprotocol = request
+            .headers()
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(|name| Encoding::from_name(name.trim()));
+        if let Some(selected) = subprotocol {
+            encoding = selected;
+            response
+                .headers_mut()
+                .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(selected.name()));
+        }
+        if let Some(selected) = Encoding::from_query(query).map_err(bad_request)? {
+            encoding = selected;
+        }
+        resume_from = resume_from_query(query).map_err(bad_request)?;
+        Ok(response)
+    })
+    .await;
+    let ws_stream = match handshake {
+        Ok(ws_stream) => ws_stream,
+        // Turned away by the callback above, which logged why
+        Err(tungstenite::Error::Http(response)) => {
+            debug!("Client {} upgrade refused with {}", client_id, response.status());
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    let key = grant.as_ref().map(|grant| grant.key().clone());
+    let allowed = key.as_ref().map_or(EventTypeSet::all(), |key| key.allowed);
 
-    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
-    clients.insert(client_id, tx);
+    {
+        let sequencer = context.sequencer.lock().unwrap();
+        context.clients.insert(
+            client_id,
+            Client {
+                queue: queue.clone(),
+                subscription: Subscription::new(allowed),
+                encoding,
+                format,
+                joined_seq: sequencer.last_seq,
+                key: key.clone(),
+            },
+        );
+    }
 
-    info!("Client {} registered", client_id);
+    match &key {
+        Some(key) => info!("Client {} registered ({}, key {})", client_id, encoding.name(), key.name),
+        None => info!("Client {} registered ({})", client_id, encoding.name()),
+    }
+    if let Some(reply) = resume_from.and_then(|from_seq| resume(&context, client_id, from_seq)) {
+        queue.push_control(Message::text(reply.to_json()).into());
+    }
+    // Registered after `shutdown` went through the clients
+    if *context.phase.borrow() != Phase::Running {
+        queue.drain();
+    }
 
+    let send_queue = queue.clone();
+    let close_queue = queue.clone();
+    let send_metrics = context.metrics.clone();
     let send_task = tokio::spawn(async move {
-        while let Some(message) = rx.recv().await {
-            if ws_sender.send(Message::Text(message.into())).await.is_err() {
+        loop {
+            let mut grpc_recv_us = None;
+            let message = match send_queue.pop().await {
+                Delivery::Message(outgoing) => {
+                    grpc_recv_us = outgoing.grpc_recv_us;
+                    outgoing.frame
+                }
+                Delivery::Lagged { dropped, total_dropped } => {
+                    warn!("Client {} lagged, dropped {} messages", client_id, dropped);
+                    let notice = ServerMessage::Lagged { dropped, total_dropped };
+                    Message::text(notice.to_json())
+                }
+                Delivery::RateLimited { dropped } => {
+                    debug!("Client {} rate limited, withheld {} messages", client_id, dropped);
+                    Message::text(ServerMessage::RateLimited { dropped }.to_json())
+                }
+                Delivery::Disconnect { lag } => {
+                    warn!("Disconnecting slow client {} with {} queued messages", client_id, lag);
+                    let _ = ws_sender
+                        .send(Message::Close(Some(CloseFrame {
+                            code: CloseCode::Policy,
+                            reason: format!("slow consumer: {} messages behind", lag).into(),
+                        })))
+                        .await;
+                    break;
+                }
+                Delivery::Drained => {
+                    let _ = ws_sender
+                        .send(Message::Close(Some(CloseFrame {
+                            code: CloseCode::Away,
+                            reason: "server shutting down".into(),
+                        })))
+                        .await;
+                    break;
+                }
+                Delivery::Closed => break,
+            };
+
+            if ws_sender.send(message).await.is_err() {
                 break;
             }
+            if let Some(recv_us) = grpc_recv_us {
+                send_metrics.event_sent(now_us() - recv_us);
+            }
         }
     });
 
+    let recv_context = context.clone();
     let recv_task = tokio::spawn(async move {
         while let Some(msg) = ws_receiver.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
-                    info!("Received from client {}: {}", client_id, text);
+                    let Some(reply) = handle_request(&recv_context, client_id, &text) else {
+                        continue;
+                    };
+                    if queue.push_control(Message::text(reply.to_json()).into()) == PushOutcome::Closed {
+                        break;
+                    }
                 }
                 Ok(Message::Close(_)) => {
                     info!("Client {} closed connection", client_id);
@@ -103,13 +868,573 @@ async fn handle_connection(
         }
     });
 
+    let send_abort = send_task.abort_handle();
+    let recv_abort = recv_task.abort_handle();
     tokio::select! {
-        _ = send_task => {},
-        _ = recv_task => {},
+        _ = send_task => recv_abort.abort(),
+        // Wakes the send task so it stops waiting for messages
+        _ = recv_task => close_queue.close(),
+        _ = reached(context.phase.clone(), Phase::Stopped) => {
+            send_abort.abort();
+            recv_abort.abort();
+        }
     }
 
-    clients.remove(&client_id);
+    context.clients.remove(&client_id);
     info!("Client {} disconnected", client_id);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// `?resume_from=<seq>`: replay from that sequence number before any live event
+fn resume_from_query(query: &str) -> Result<Option<u64>, String> {
+    for pair in query.split('&') {
+        if let Some(("resume_from", value)) = pair.split_once('=') {
+            return value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid resume_from: {}", value));
+        }
+    }
+    Ok(None)
+}
+
+fn bad_request(message: String) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(message));
+    *response.status_mut() = StatusCode::BAD_REQUEST;
+    response
+}
+
+/// 401 for a missing or unknown API key, 429 when the key is at its connection limit
+fn reject(client_id: ClientId, rejection: Rejection) -> ErrorResponse {
+    let (status, message) = match rejection {
+        Rejection::Unauthorized => (StatusCode::UNAUTHORIZED, "missing or invalid API key".to_string()),
+        Rejection::TooManyConnections { name, limit } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("API key {} is limited to {} connections", name, limit),
+        ),
+    };
+    // Anyone can knock without a key, so only a key at its limit is worth more than debug
+    match status {
+        StatusCode::UNAUTHORIZED => debug!("Rejected client {}: {}", client_id, message),
+        _ => info!("Rejected client {}: {}", client_id, message),
+    }
+
+    let mut response = ErrorResponse::new(Some(message));
+    *response.status_mut() = status;
+    if status == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+/// Apply a control message to the client's subscription and build the
+/// reply; `None` when the reply was already queued, as `resume` does
+fn handle_request(context: &Context, client_id: ClientId, text: &str) -> Option<ServerMessage> {
+    let request: ClientRequest = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(e) => {
+            warn!("Invalid request from client {}: {}", client_id, e);
+            return Some(ServerMessage::error(format!("invalid request: {}", e)));
+        }
+    };
+
+    // Takes the sequencer before the client entry, like `broadcast_event`
+    if let ClientRequest::Resume { from_seq } = request {
+        return resume(context, client_id, from_seq);
+    }
+
+    let Some(mut client) = context.clients.get_mut(&client_id) else {
+        return Some(ServerMessage::error("client is not registered"));
+    };
+    Some(apply_request(&mut client, context, client_id, request))
+}
+
+fn apply_request(client: &mut Client, context: &Context, client_id: ClientId, request: ClientRequest) -> ServerMessage {
+    let normalize_trades = context.normalize_trades;
+
+    match request {
+        ClientRequest::Subscribe { event_types } => match parse_streams(&event_types) {
+            Ok(streams) if streams.trades && !normalize_trades => {
+                return ServerMessage::error(format!(
+                    "{} is disabled on this server",
+                    NORMALIZED_TRADE
+                ));
+            }
+            Ok(streams) => {
+                let allowed = client.subscription.allowed;
+                let denied: Vec<_> = streams
+                    .event_types
+                    .iter()
+                    .filter(|t| !allowed.contains(**t))
+                    .map(|t| event_type_name(*t))
+                    .collect();
+                if !denied.is_empty() {
+                    return ServerMessage::error(format!("not entitled to: {}", denied.join(", ")));
+                }
+                client.subscription.subscribe(&streams)
+            }
+            Err(message) => return ServerMessage::error(message),
+        },
+        ClientRequest::Unsubscribe { event_types } => match parse_streams(&event_types) {
+            Ok(streams) => client.subscription.unsubscribe(&streams),
+            Err(message) => return ServerMessage::error(message),
+        },
+        ClientRequest::ListSubscriptions => {}
+        ClientRequest::SetFilter { filter } => match ClientFilter::from_spec(&filter) {
+            Ok(filter) => client.subscription.filter = filter,
+            Err(message) => return ServerMessage::error(message),
+        },
+        ClientRequest::ClearFilter => client.subscription.filter = ClientFilter::default(),
+        ClientRequest::SetPolicy { policy } => {
+            client.queue.set_policy(policy);
+            info!("Client {} slow-consumer policy: {:?}", client_id, policy);
+            return ServerMessage::Policy {
+                policy: client.queue.policy(),
+            };
+        }
+        ClientRequest::SetFormat { format } => {
+            client.format = format;
+            info!("Client {} output format: {:?}", client_id, format);
+            return ServerMessage::Format { format };
+        }
+        ClientRequest::Status => {
+            return ServerMessage::Upstream {
+                connected: context.upstream.is_connected(),
+                reconnects: context.upstream.reconnects(),
+            };
+        }
+        ClientRequest::Resume { .. } => unreachable!("handled above"),
+    }
+
+    info!(
+        "Client {} subscriptions: {:?}",
+        client_id,
+        client.subscription.names()
+    );
+
+    ServerMessage::Subscriptions {
+        event_types: client.subscription.names(),
+        filter: client.subscription.filter.to_spec(),
+    }
+}
+
+/// Replay the buffered events from `from_seq` through the last event sent
+/// before the client registered, ahead of the live events queued since.
+///
+/// Under the sequencer the buffer is snapshotted and the client's live
+/// delivery held; the frames are encoded without the lock, so a long replay
+/// doesn't stall the broadcast. Replayed frames bypass the slow-consumer
+/// policy, being bounded by `replay_capacity`, and the `resumed` reply
+/// follows them. Live events already delivered can't be put back in order,
+/// so resuming after one is refused.
+fn resume(context: &Context, client_id: ClientId, from_seq: u64) -> Option<ServerMessage> {
+    let (client, entries, complete) = {
+        let sequencer = context.sequencer.lock().unwrap();
+        let Some(replay) = &sequencer.replay else {
+            return Some(ServerMessage::error("replay is disabled on this server"));
+        };
+        let Some(client) = context.clients.get(&client_id) else {
+            return Some(ServerMessage::error("client is not registered"));
+        };
+        if client.queue.has_delivered_events() {
+            return Some(ServerMessage::error(
+                "resume must be requested before the first event, reconnect with ?resume_from=<seq>",
+            ));
+        }
+        client.queue.hold();
+        let entries: Vec<_> = replay.range(from_seq, client.joined_seq).cloned().collect();
+        (client.clone(), entries, replay.covers(from_seq))
+    };
+
+    let metrics = &context.metrics;
+    let subscription = &client.subscription;
+    let mut replay = Replay::default();
+    for entry in &entries {
+        if !subscription.matches_filter(&event_keys(&entry.event)) {
+            continue;
+        }
+        if subscription.event_types.contains(entry.event_type) && replay.within_rate(&client, metrics) {
+            replay.push(Frames::new(&entry.event, entry.header, &entry.labels, metrics).get(client.encoding, client.format));
+        }
+        if context.normalize_trades && subscription.wants_trade(entry.event_type) {
+            if let Some(trade) = normalize_trade(&entry.event).filter(|_| replay.within_rate(&client, metrics)) {
+                let trade = NormalizedEvent::NormalizedTrade(trade);
+                let header = Header {
+                    event_type: NORMALIZED_TRADE,
+                    ..entry.header
+                };
+                replay.push(Frames::new(&trade, header, &entry.labels, metrics).get(client.encoding, client.format));
+            }
+        }
+    }
+
+    let through_seq = client.joined_seq;
+    info!(
+        "Client {} resumed from {} through {}: {} events",
+        client_id, from_seq, through_seq, replay.replayed
+    );
+    let reply = ServerMessage::Resumed {
+        from_seq,
+        through_seq,
+        replayed: replay.replayed,
+        complete,
+    };
+    client.queue.release(replay.finish(reply).into_iter().map(Outgoing::from));
+    None
+}
+
+/// Frames of a replay, with rate-limited events reported in place the way
+/// `ClientQueue` reports them for live events
+#[derive(Default)]
+struct Replay {
+    frames: Vec<Message>,
+    limited: u64,
+    replayed: usize,
+}
+
+impl Replay {
+    fn within_rate(&mut self, client: &Client, metrics: &Metrics) -> bool {
+        let allowed = client.key.as_ref().is_none_or(|key| key.allow_message());
+        if !allowed {
+            metrics.message_rate_limited();
+            self.limited += 1;
+        }
+        allowed
+    }
+
+    fn push(&mut self, frame: Option<&Message>) {
+        if let Some(frame) = frame {
+            self.flush_limited();
+            self.frames.push(frame.clone());
+            self.replayed += 1;
+        }
+    }
+
+    fn flush_limited(&mut self) {
+        if self.limited > 0 {
+            let dropped = std::mem::take(&mut self.limited);
+            self.frames.push(Message::text(ServerMessage::RateLimited { dropped }.to_json()));
+        }
+    }
+
+    fn finish(mut self, reply: ServerMessage) -> Vec<Message> {
+        self.flush_limited();
+        self.frames.push(Message::text(reply.to_json()));
+        self.frames
+    }
+}
+
+fn parse_streams(names: &[String]) -> Result<Streams, String> {
+    let mut streams = Streams {
+        event_types: Vec::with_capacity(names.len()),
+        trades: false,
+    };
+    let mut unknown = Vec::new();
+
+    for name in names {
+        if name == NORMALIZED_TRADE {
+            streams.trades = true;
+            continue;
+        }
+        match parse_event_type(name) {
+            Some(event_type) => streams.event_types.push(event_type),
+            None => unknown.push(name.as_str()),
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(streams)
+    } else {
+        Err(format!("unknown event types: {}", unknown.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiKeyConfig, Config, SlowConsumerPolicy};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use crate::test_support::block_meta;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::Error as WsError;
+
+    /// Register a client the way `handle_connection` does, without a socket
+    fn register(server: &WsServer, client_id: ClientId) -> Arc<ClientQueue<Outgoing>> {
+        let queue = Arc::new(ClientQueue::new(16, 16, SlowConsumerPolicy::DropOldest));
+        let sequencer = server.sequencer.lock().unwrap();
+        server.clients.insert(
+            client_id,
+            Client {
+                queue: queue.clone(),
+                subscription: Subscription::new(EventTypeSet::all()),
+                encoding: Encoding::Json,
+                format: OutputFormat {
+                    envelope: true,
+                    ..OutputFormat::default()
+                },
+                joined_seq: sequencer.last_seq,
+                key: None,
+            },
+        );
+        queue
+    }
+
+    async fn next_json(queue: &ClientQueue<Outgoing>) -> serde_json::Value {
+        match queue.pop().await {
+            Delivery::Message(message) => serde_json::from_str(message.frame.to_text().unwrap()).unwrap(),
+            _ => panic!("expected a message"),
+        }
+    }
+
+    fn request(context: &Context, text: &str) -> serde_json::Value {
+        let reply = handle_request(context, 0, text).expect("a reply");
+        serde_json::from_str(&reply.to_json()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_streams() {
+        let names = ["PumpFunTrade".to_string(), NORMALIZED_TRADE.to_string()];
+        let streams = parse_streams(&names).unwrap();
+        assert_eq!(streams.event_types, vec![EventType::PumpFunTrade]);
+        assert!(streams.trades);
+
+        let names = ["PumpFunTrade".to_string(), "Nope".to_string(), "Nada".to_string()];
+        assert_eq!(parse_streams(&names).err().unwrap(), "unknown event types: Nope, Nada");
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let server = WsServer::new(&Config::default().server);
+        let context = server.context();
+        assert_eq!(request(&context, "{}")["type"], "error");
+        assert_eq!(request(&context, r#"{"action": "status"}"#)["message"], "client is not registered");
+
+        register(&server, 0);
+        let reply = request(&context, r#"{"action": "subscribe", "event_types": ["PumpFunTrade"]}"#);
+        assert_eq!(reply["type"], "subscriptions");
+        assert_eq!(reply["event_types"], json!(["PumpFunTrade"]));
+
+        let reply = request(&context, r#"{"action": "subscribe", "event_types": ["NormalizedTrade"]}"#);
+        assert_eq!(reply["message"], "NormalizedTrade is disabled on this server");
+
+        let reply = request(&context, r#"{"action": "set_policy", "policy": "drop_newest"}"#);
+        assert_eq!(reply, json!({"type": "policy", "policy": "drop_newest"}));
+
+        let reply = request(&context, r#"{"action": "resume", "from_seq": 1}"#);
+        assert_eq!(reply["message"], "replay is disabled on this server");
+    }
+
+    #[tokio::test]
+    async fn test_resume() {
+        let mut config = Config::default().server;
+        config.replay_capacity = 8;
+        let server = WsServer::new(&config);
+        let context = server.context();
+        for slot in 1..=3 {
+            server.broadcast_event(&block_meta(slot));
+        }
+        let queue = register(&server, 0);
+        server.broadcast_event(&block_meta(4));
+
+        // The missed events come first, then the reply, then the live event
+        assert!(resume(&context, 0, 2).is_none());
+        for seq in [2, 3] {
+            assert_eq!(next_json(&queue).await["seq"], seq);
+        }
+        let reply = next_json(&queue).await;
+        assert_eq!(
+            reply,
+            json!({"type": "resumed", "from_seq": 2, "through_seq": 3, "replayed": 2, "complete": true})
+        );
+        assert_eq!(next_json(&queue).await["seq"], 4);
+
+        let reply = resume(&context, 0, 2).unwrap();
+        assert!(matches!(reply, ServerMessage::Error { message } if message.contains("resume_from")));
+    }
+
+    #[tokio::test]
+    async fn test_in_process_subscription() {
+        let server = WsServer::new(&Config::default().server);
+        let subscription = |names: &[&str]| EventSubscription {
+            event_types: names.iter().map(|name| name.to_string()).collect(),
+            ..EventSubscription::default()
+        };
+        assert!(server.subscribe(&subscription(&["Nope"])).is_err());
+        assert!(server.subscribe(&subscription(&[NORMALIZED_TRADE])).is_err());
+
+        let mut blocks = server.subscribe(&subscription(&["BlockMeta"])).unwrap();
+        let trades = server.subscribe(&subscription(&["PumpFunTrade"])).unwrap();
+        server.broadcast_event(&block_meta(7));
+        let queued: usize = server.subscribers.iter().map(|entry| entry.queue.len()).sum();
+        assert_eq!(queued, 1);
+        assert!(matches!(blocks.recv().await, Some(DexEvent::BlockMeta(e)) if e.metadata.slot == 7));
+
+        drop(trades);
+        assert_eq!(server.subscribers.len(), 1);
+        server.shutdown(Duration::from_secs(1)).await;
+        assert!(blocks.recv().await.is_none());
+    }
+
+    #[test]
+    fn test_http_response() {
+        let server = WsServer::new(&Config::default().server);
+        let context = server.context();
+        let status = |method, path| http_response(&context, method, path).status;
+
+        assert_eq!(status("GET", "/healthz"), StatusCode::OK);
+        assert_eq!(status("HEAD", "/info"), StatusCode::OK);
+        assert_eq!(status("GET", "/readyz"), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("GET", "/nope"), StatusCode::NOT_FOUND);
+        assert_eq!(status("POST", "/healthz"), StatusCode::METHOD_NOT_ALLOWED);
+
+        let metrics = http_response(&context, "GET", "/metrics");
+        assert_eq!(metrics.status, StatusCode::OK);
+        assert_eq!(metrics.content_type, http::PROMETHEUS_TEXT);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_then_closes() {
+        let server = Arc::new(WsServer::new(&Config::default().server));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept_server = server.clone();
+        let accept_task = tokio::spawn(async move { accept_server.run(listener).await });
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        while server.clients.is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        server.broadcast_event(&block_meta(1));
+        server.shutdown(Duration::from_secs(5)).await;
+        accept_task.await.unwrap();
+
+        assert!(matches!(ws.next().await, Some(Ok(Message::Text(_)))));
+        let Some(Ok(Message::Close(Some(frame)))) = ws.next().await else {
+            panic!("expected a close frame");
+        };
+        assert_eq!(frame.code, CloseCode::Away);
+        // Measured when the frame was written, not when it was queued
+        let metrics = http_response(&server.context(), "GET", "/metrics").body;
+        assert!(metrics.contains("parser_proxy_event_send_latency_seconds_count 1"), "{}", metrics);
+        assert!(tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_request_is_closed() {
+        let config = ServerConfig {
+            handshake_timeout_ms: 50,
+            ..Config::default().server
+        };
+        let server = Arc::new(WsServer::new(&config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept_server = server.clone();
+        tokio::spawn(async move { accept_server.run(listener).await });
+
+        // Half a request head, then nothing
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /info HTTP/1.1\r\n").await.unwrap();
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut buf)).await;
+        assert!(matches!(read, Ok(Ok(0))), "the server kept the connection open: {:?}", read);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_rejections() {
+        let auth = AuthConfig {
+            keys: vec![ApiKeyConfig {
+                key: "secret".to_string(),
+                name: Some("test".to_string()),
+                protocols: Vec::new(),
+                event_types: Vec::new(),
+                max_connections: Some(1),
+                max_messages_per_sec: None,
+            }],
+            keys_file: None,
+        };
+        let server = Arc::new(WsServer::new(&Config::default().server).with_auth(&auth).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept_server = server.clone();
+        tokio::spawn(async move { accept_server.run(listener).await });
+
+        let connect = |key: Option<&str>| {
+            let mut request = format!("ws://{}", addr).into_client_request().unwrap();
+            if let Some(key) = key {
+                request
+                    .headers_mut()
+                    .insert("authorization", format!("Bearer {}", key).parse().unwrap());
+            }
+            tokio_tungstenite::connect_async(request)
+        };
+        let status = |result: Result<_, WsError>| match result {
+            Err(WsError::Http(response)) => response.status(),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => StatusCode::SWITCHING_PROTOCOLS,
+        };
+
+        assert_eq!(status(connect(None).await), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(connect(Some("wrong")).await), StatusCode::UNAUTHORIZED);
+        let (_first, _) = connect(Some("secret")).await.unwrap();
+        assert_eq!(status(connect(Some("secret")).await), StatusCode::TOO_MANY_REQUESTS);
+
+        // A refused upgrade ends the connection cleanly, not as an error
+        let (mut client, stream) = tokio::io::duplex(4096);
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let queue = Arc::new(ClientQueue::new(16, 16, SlowConsumerPolicy::DropOldest));
+        let served = serve(stream, addr, 99, queue, server.context(), None).await;
+        assert!(served.is_ok(), "{:?}", served);
+        let mut response = vec![0; 12];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(response, b"HTTP/1.1 401");
+    }
+
+    #[tokio::test]
+    async fn test_keyed_endpoints() {
+        let auth = AuthConfig {
+            keys: vec![ApiKeyConfig {
+                key: "secret".to_string(),
+                name: None,
+                protocols: Vec::new(),
+                event_types: Vec::new(),
+                max_connections: None,
+                max_messages_per_sec: None,
+            }],
+            keys_file: None,
+        };
+        let server = WsServer::new(&Config::default().server).with_auth(&auth).unwrap();
+        let addr = "127.0.0.1:1".parse().unwrap();
+        let status = |target: &'static str, key: Option<&'static str>| {
+            let context = server.context();
+            async move {
+                let (mut client, stream) = tokio::io::duplex(64 * 1024);
+                let key = key.map(|key| format!("X-API-Key: {}\r\n", key)).unwrap_or_default();
+                let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n", target, key);
+                client.write_all(request.as_bytes()).await.unwrap();
+                let queue = Arc::new(ClientQueue::new(16, 16, SlowConsumerPolicy::DropOldest));
+                serve(stream, addr, 99, queue, context, None).await.unwrap();
+                let mut response = vec![0; 12];
+                client.read_exact(&mut response).await.unwrap();
+                String::from_utf8(response).unwrap()
+            }
+        };
+
+        assert_eq!(status("/healthz", None).await, "HTTP/1.1 200");
+        assert_eq!(status("/readyz", None).await, "HTTP/1.1 503");
+        for path in ["/info", "/metrics"] {
+            assert_eq!(status(path, None).await, "HTTP/1.1 401");
+            assert_eq!(status(path, Some("wrong")).await, "HTTP/1.1 401");
+            assert_eq!(status(path, Some("secret")).await, "HTTP/1.1 200");
+        }
+        assert_eq!(status("/metrics?api_key=secret", None).await, "HTTP/1.1 200");
+    }
+}
@@ -76,14 +76,9 @@ case $choice in
             pip3 install websockets --quiet
         fi
 
-        if ! python3 -c "import base58" 2>/dev/null; then
-            echo "Installing base58 package..."
-            pip3 install base58 --quiet
-        fi
-
         if [ -f "examples/client.py" ]; then
             echo "✅ Starting Python client..."
-            echo "✨ This client converts Pubkey/Signature to base58 strings"
+            echo "✨ This client requests base58 Pubkey/Signature from the server"
             echo "   Press Ctrl+C to stop"
             echo ""
             echo "================================================"